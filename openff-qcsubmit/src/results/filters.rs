@@ -1,27 +1,71 @@
+use std::collections::{HashMap, HashSet};
+
+use openff_toolkit::utils::charge_cache::ChargeCache;
 use pyo3::{
-    types::{IntoPyDict, PyModule},
-    FromPyObject, IntoPy, Py, PyAny, Python,
+    types::{IntoPyDict, PyDict},
+    FromPyObject, IntoPy, Py, PyAny, PyResult, Python,
 };
-use qcportal::record_models::RecordStatus;
+use qcportal::record_models::{Record, RecordStatus};
+use utils::import_attr;
 
-use super::BaseResultCollection;
+use super::{BaseResultCollection, Entry};
 
 const PYMODULE: &str = "openff.qcsubmit.results.filters";
 
+/// Construct the QCSubmit filter `class` with `kwargs`.
+fn construct<'a, T: FromPyObject<'a>>(
+    py: Python<'a>,
+    class: &'static str,
+    kwargs: Option<&PyDict>,
+) -> T {
+    import_attr(py, PYMODULE, class)
+        .unwrap()
+        .call((), kwargs)
+        .unwrap()
+        .extract()
+        .unwrap()
+}
+
+/// Construct one of the QCSubmit filters that take either a list of values to
+/// include, under `{field}_to_include`, or a list to exclude, under
+/// `{field}_to_exclude`, but not both.
+fn include_or_exclude<T: for<'a> FromPyObject<'a>>(
+    class: &'static str,
+    field: &str,
+    exclude: bool,
+    values: impl IntoPy<Py<PyAny>>,
+) -> T {
+    let which = if exclude { "exclude" } else { "include" };
+    Python::with_gil(|py| {
+        let kwargs = [(format!("{field}_to_{which}"), values.into_py(py))]
+            .into_py_dict(py);
+        construct(py, class, Some(kwargs))
+    })
+}
+
+#[derive(FromPyObject)]
+pub struct ChargeFilter(Py<PyAny>);
+
+impl ChargeFilter {
+    /// Keep only molecules with one of the total formal `charges`.
+    pub fn include(charges: Vec<i32>) -> Self {
+        include_or_exclude("ChargeFilter", "charges", false, charges)
+    }
+
+    /// Remove molecules with any of the total formal `charges`.
+    pub fn exclude(charges: Vec<i32>) -> Self {
+        include_or_exclude("ChargeFilter", "charges", true, charges)
+    }
+}
+
 #[derive(FromPyObject)]
 pub struct ConformerRMSDFilter(Py<PyAny>);
 
 impl ConformerRMSDFilter {
     pub fn new(max_conformers: usize) -> Self {
         Python::with_gil(|py| {
-            let m = PyModule::import(py, PYMODULE).unwrap();
             let kwargs = [("max_conformers", max_conformers)].into_py_dict(py);
-            m.getattr("ConformerRMSDFilter")
-                .unwrap()
-                .call((), Some(kwargs))
-                .unwrap()
-                .extract()
-                .unwrap()
+            construct(py, "ConformerRMSDFilter", Some(kwargs))
         })
     }
 }
@@ -32,14 +76,8 @@ pub struct ConnectivityFilter(Py<PyAny>);
 impl ConnectivityFilter {
     pub fn new(tolerance: f64) -> Self {
         Python::with_gil(|py| {
-            let m = PyModule::import(py, PYMODULE).unwrap();
             let kwargs = [("tolerance", tolerance)].into_py_dict(py);
-            m.getattr("ConnectivityFilter")
-                .unwrap()
-                .call((), Some(kwargs))
-                .unwrap()
-                .extract()
-                .unwrap()
+            construct(py, "ConnectivityFilter", Some(kwargs))
         })
     }
 }
@@ -50,55 +88,148 @@ pub struct ElementFilter(Py<PyAny>);
 impl ElementFilter {
     pub fn new(elements: Vec<&str>) -> Self {
         Python::with_gil(|py| {
-            let m = PyModule::import(py, PYMODULE).unwrap();
             let kwargs = [("allowed_elements", elements)].into_py_dict(py);
-            m.getattr("ElementFilter")
-                .unwrap()
-                .call((), Some(kwargs))
-                .unwrap()
-                .extract()
-                .unwrap()
+            construct(py, "ElementFilter", Some(kwargs))
         })
     }
 }
 
+/// The methods [HydrogenBondFilter] can use to detect hydrogen bonds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HydrogenBondMethod {
+    /// The Baker-Hubbard criterion as implemented in MDTraj.
+    #[default]
+    BakerHubbard,
+}
+
+impl HydrogenBondMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HydrogenBondMethod::BakerHubbard => "baker-hubbard",
+        }
+    }
+}
+
+/// Removes records whose final structures contain internal hydrogen bonds.
 #[derive(FromPyObject)]
 pub struct HydrogenBondFilter(Py<PyAny>);
 
+impl HydrogenBondFilter {
+    pub fn new(method: HydrogenBondMethod) -> Self {
+        Python::with_gil(|py| {
+            let kwargs = [("method", method.as_str())].into_py_dict(py);
+            construct(py, "HydrogenBondFilter", Some(kwargs))
+        })
+    }
+}
+
+impl Default for HydrogenBondFilter {
+    fn default() -> Self {
+        Self::new(HydrogenBondMethod::default())
+    }
+}
+
+/// Keeps only the lowest energy conformer of each molecule. This only
+/// applies to optimization collections.
+#[derive(FromPyObject)]
+pub struct LowestEnergyFilter(Py<PyAny>);
+
+impl LowestEnergyFilter {
+    pub fn new() -> Self {
+        Python::with_gil(|py| construct(py, "LowestEnergyFilter", None))
+    }
+}
+
+impl Default for LowestEnergyFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Removes molecules with fewer than `min_conformers` conformers.
+#[derive(FromPyObject)]
+pub struct MinimumConformersFilter(Py<PyAny>);
+
+impl MinimumConformersFilter {
+    pub fn new(min_conformers: usize) -> Self {
+        Python::with_gil(|py| {
+            let kwargs = [("min_conformers", min_conformers)].into_py_dict(py);
+            construct(py, "MinimumConformersFilter", Some(kwargs))
+        })
+    }
+}
+
 #[derive(FromPyObject)]
 pub struct RecordStatusFilter(Py<PyAny>);
 
 impl RecordStatusFilter {
     pub fn new(status: RecordStatus) -> Self {
         Python::with_gil(|py| {
-            let m = PyModule::import(py, PYMODULE).unwrap();
             let kwargs = [("status", status.into_py(py))].into_py_dict(py);
-            m.getattr("RecordStatusFilter")
-                .unwrap()
-                .call((), Some(kwargs))
-                .unwrap()
-                .extract()
-                .unwrap()
+            construct(py, "RecordStatusFilter", Some(kwargs))
         })
     }
 }
 
+/// The abstract base class of QCSubmit filters that inspect each record and
+/// its molecule. It has no constructor because Python cannot instantiate it,
+/// but instances of subclasses defined in Python can be extracted into it and
+/// applied.
 #[derive(FromPyObject)]
 pub struct ResultRecordFilter(Py<PyAny>);
 
+/// The abstract base class of QCSubmit filters that inspect all of the
+/// records for a molecule at once, like [LowestEnergyFilter]. As for
+/// [ResultRecordFilter], only instances of subclasses can be extracted into
+/// it.
+#[derive(FromPyObject)]
+pub struct ResultRecordGroupFilter(Py<PyAny>);
+
+#[derive(FromPyObject)]
+pub struct SMARTSFilter(Py<PyAny>);
+
+impl SMARTSFilter {
+    /// Keep only molecules matching at least one of the `smarts` patterns.
+    pub fn include(smarts: Vec<&str>) -> Self {
+        include_or_exclude("SMARTSFilter", "smarts", false, smarts)
+    }
+
+    /// Remove molecules matching any of the `smarts` patterns.
+    pub fn exclude(smarts: Vec<&str>) -> Self {
+        include_or_exclude("SMARTSFilter", "smarts", true, smarts)
+    }
+}
+
+#[derive(FromPyObject)]
+pub struct SMILESFilter(Py<PyAny>);
+
+impl SMILESFilter {
+    /// Keep only molecules that are the same as one of `smiles`.
+    pub fn include(smiles: Vec<&str>) -> Self {
+        include_or_exclude("SMILESFilter", "smiles", false, smiles)
+    }
+
+    /// Remove molecules that are the same as any of `smiles`.
+    pub fn exclude(smiles: Vec<&str>) -> Self {
+        include_or_exclude("SMILESFilter", "smiles", true, smiles)
+    }
+}
+
 #[derive(FromPyObject)]
 pub struct UnperceivableStereoFilter(Py<PyAny>);
 
 impl UnperceivableStereoFilter {
+    /// Check stereochemistry with both OpenEye and RDKit, QCSubmit's default.
     pub fn new() -> Self {
+        Python::with_gil(|py| construct(py, "UnperceivableStereoFilter", None))
+    }
+
+    /// Check stereochemistry with each of `toolkits`, which may include
+    /// `openeye` and `rdkit`.
+    pub fn with_toolkits(toolkits: Vec<&str>) -> Self {
         Python::with_gil(|py| {
-            let m = PyModule::import(py, PYMODULE).unwrap();
-            m.getattr("UnperceivableStereoFilter")
-                .unwrap()
-                .call0()
-                .unwrap()
-                .extract()
-                .unwrap()
+            let kwargs = [("toolkits", toolkits)].into_py_dict(py);
+            construct(py, "UnperceivableStereoFilter", Some(kwargs))
         })
     }
 }
@@ -109,6 +240,146 @@ impl Default for UnperceivableStereoFilter {
     }
 }
 
+/// A native filter that keeps or removes entries by the InChIKeys of their
+/// molecules. QCSubmit has no equivalent, so this is not recorded in the
+/// `applied-filters` provenance.
+pub struct InChIKeyFilter {
+    keys: HashSet<String>,
+    exclude: bool,
+}
+
+impl InChIKeyFilter {
+    /// Keep only entries with one of `keys`.
+    pub fn include<S: Into<String>>(keys: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            keys: keys.into_iter().map(Into::into).collect(),
+            exclude: false,
+        }
+    }
+
+    /// Remove entries with any of `keys`.
+    pub fn exclude<S: Into<String>>(keys: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            exclude: true,
+            ..Self::include(keys)
+        }
+    }
+}
+
+impl<T: BaseResultCollection> Filter<T> for InChIKeyFilter {
+    fn apply(&self, dataset: T) -> T {
+        retain(dataset, |entry| {
+            self.keys.contains(&entry.inchi_key) != self.exclude
+        })
+    }
+}
+
+/// A native filter that keeps or removes entries by record ID. Like
+/// [InChIKeyFilter], this is not recorded in the provenance.
+pub struct RecordIDFilter {
+    ids: HashSet<usize>,
+    exclude: bool,
+}
+
+impl RecordIDFilter {
+    /// Keep only entries with one of the record `ids`.
+    pub fn include(ids: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            ids: ids.into_iter().collect(),
+            exclude: false,
+        }
+    }
+
+    /// Remove entries with any of the record `ids`.
+    pub fn exclude(ids: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            exclude: true,
+            ..Self::include(ids)
+        }
+    }
+}
+
+impl<T: BaseResultCollection> Filter<T> for RecordIDFilter {
+    fn apply(&self, dataset: T) -> T {
+        retain(dataset, |entry| {
+            self.ids.contains(&entry.record_id) != self.exclude
+        })
+    }
+}
+
+/// Keep only the entries of `dataset` for which `keep` returns true.
+fn retain<T: BaseResultCollection>(
+    mut dataset: T,
+    mut keep: impl FnMut(&Entry) -> bool,
+) -> T {
+    let mut entries = dataset.entries();
+    for entries in entries.values_mut() {
+        entries.retain(&mut keep);
+    }
+    dataset.set_entries(entries);
+    dataset
+}
+
+/// A native filter that removes entries whose molecules cannot be assigned
+/// partial charges with the given method, like the `ChargeCheckFilter` in
+/// `python/filters.py`. As there, the molecules come from the records, and
+/// only charge calculation and conformer generation errors remove an entry.
+/// Charges and failures are looked up in and added to [ChargeCache::global],
+/// so repeated runs only compute them once. Unlike the Python filter, which
+/// always uses OpenEye, charges are assigned by the first toolkit that
+/// supports the method, which is still OpenEye for `am1bccelf10`.
+pub struct ChargeCheckFilter {
+    method: String,
+}
+
+impl ChargeCheckFilter {
+    pub fn new(partial_charge_method: &str) -> Self {
+        Self {
+            method: partial_charge_method.to_owned(),
+        }
+    }
+
+    /// Like [Filter::apply], but return any error other than a failure to
+    /// assign charges to a molecule, such as an unsupported method or a
+    /// missing toolkit.
+    pub fn try_apply<T: BaseResultCollection>(
+        &self,
+        dataset: T,
+    ) -> PyResult<T> {
+        let mut failed = HashSet::new();
+        for (record, mut molecule) in dataset.to_records() {
+            let assigned = ChargeCache::get_or_assign_shared(
+                ChargeCache::global(),
+                &mut molecule,
+                &self.method,
+            )?;
+            if !assigned {
+                failed.insert(record.id());
+            }
+        }
+        let mut dataset =
+            retain(dataset, |entry| !failed.contains(&entry.record_id));
+        dataset.record_filter(
+            "ChargeCheckFilter",
+            HashMap::from([("partial_charge_method", self.method.as_str())]),
+        );
+        Ok(dataset)
+    }
+}
+
+impl Default for ChargeCheckFilter {
+    /// Check for `am1bccelf10` charges, as used in the Sage fitting filters.
+    fn default() -> Self {
+        Self::new("am1bccelf10")
+    }
+}
+
+impl<T: BaseResultCollection> Filter<T> for ChargeCheckFilter {
+    fn apply(&self, dataset: T) -> T {
+        self.try_apply(dataset).unwrap()
+    }
+}
+
 pub trait Filter<T: BaseResultCollection> {
     /// apply is actually fairly complicated to implement because the base
     /// `apply` method in Python calls the private `_apply` method, which in
@@ -150,11 +421,17 @@ macro_rules! make_filter {
 }
 
 make_filter! {
+    ChargeFilter,
     ConformerRMSDFilter,
     ConnectivityFilter,
     ElementFilter,
     HydrogenBondFilter,
+    LowestEnergyFilter,
+    MinimumConformersFilter,
     RecordStatusFilter,
     ResultRecordFilter,
+    ResultRecordGroupFilter,
+    SMARTSFilter,
+    SMILESFilter,
     UnperceivableStereoFilter,
 }
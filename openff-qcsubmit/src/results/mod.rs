@@ -3,43 +3,157 @@ use std::{collections::HashMap, error::Error};
 
 use openff_toolkit::Molecule;
 use pyo3::{
-    types::{IntoPyDict, PyModule},
-    FromPyObject, IntoPy, Py, PyAny, Python,
+    exceptions::PyValueError,
+    types::{IntoPyDict, PyDict},
+    FromPyObject, IntoPy, Py, PyAny, PyResult, Python,
+};
+use qcportal::record_models::{
+    OptimizationRecord, Record, SinglepointRecord, TorsiondriveRecord,
 };
-use qcportal::record_models::TorsiondriveRecord;
 use qcportal::PortalClient;
-use utils::{get_props, into_py};
+use serde::{Deserialize, Deserializer, Serialize};
+use utils::{import_attr, into_py, provenance::Provenance};
 
 use filters::Filter;
 
 pub mod filters;
+pub mod native;
+pub mod summary;
 
 const PYMODULE: &str = "openff.qcsubmit.results";
 
+/// The key under which [Provenance] is recorded in a collection's
+/// `provenance` block, next to QCSubmit's own `applied-filters`.
+pub const SOFTWARE_KEY: &str = "software";
+
 pub trait BaseResultCollection
 where
     Self: Sized,
 {
-    type RecordType: for<'a> FromPyObject<'a>;
+    type RecordType: for<'a> FromPyObject<'a> + Record;
     fn parse_file(filename: impl AsRef<Path>) -> Result<Self, Box<dyn Error>>;
+    /// Return the entries of the collection keyed by server address.
     fn entries(&self) -> HashMap<String, Vec<Entry>>;
+    /// Replace the entries of the collection. Entries are stored under their
+    /// key in `entries`, regardless of their [Entry::server] fields.
+    fn set_entries(&mut self, entries: HashMap<String, Vec<Entry>>);
     fn to_records(&self) -> Vec<(Self::RecordType, Molecule)>;
+    /// Add the filter `name` with `params` to the applied filters in the
+    /// provenance of the collection, numbered after those already applied as
+    /// QCSubmit does, like `RecordStatusFilter-1`.
+    fn record_filter(&mut self, name: &str, params: impl IntoPy<Py<PyAny>>);
 }
 
-#[derive(Clone, FromPyObject)]
-pub struct Entry(Py<PyAny>);
+/// The kind of record an [Entry] refers to, serialized as the `type` field of
+/// the entry.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryType {
+    Basic,
+    Optimization,
+    Torsion,
+}
 
-impl IntoPy<Py<PyAny>> for Entry {
-    fn into_py(self, _py: Python<'_>) -> Py<PyAny> {
-        self.0
+impl EntryType {
+    /// Return the name of the QCSubmit class for entries of this type.
+    fn python_class(&self) -> &'static str {
+        match self {
+            EntryType::Basic => "BasicResult",
+            EntryType::Optimization => "OptimizationResult",
+            EntryType::Torsion => "TorsionDriveResult",
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for EntryType {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract::<&str>()? {
+            "basic" => Ok(EntryType::Basic),
+            "optimization" => Ok(EntryType::Optimization),
+            "torsion" => Ok(EntryType::Torsion),
+            s => {
+                Err(PyValueError::new_err(format!("unknown entry type `{s}`")))
+            }
+        }
+    }
+}
+
+/// A reference to a single record in a result collection, along with enough
+/// information to build its molecule without retrieving the record. The
+/// fields are serialized in the same order as QCSubmit, except for `server`,
+/// which is the key of the collection's `entries` map that the entry belongs
+/// to rather than part of the entry itself.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Entry {
+    #[serde(rename = "type")]
+    pub kind: EntryType,
+    /// Older collections stored the record ID as a string, so either form
+    /// is accepted when deserializing. It is always serialized as an integer,
+    /// as QCSubmit does now.
+    #[serde(deserialize_with = "record_id")]
+    pub record_id: usize,
+    /// The mapped SMILES of the molecule, with map numbers giving the order of
+    /// the atoms in the QCArchive molecule.
+    pub cmiles: String,
+    pub inchi_key: String,
+    /// The address of the QCArchive server holding the record.
+    #[serde(skip)]
+    pub server: String,
+}
+
+fn record_id<'de, D: Deserializer<'de>>(d: D) -> Result<usize, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RecordId {
+        Int(usize),
+        Str(String),
+    }
+    match RecordId::deserialize(d)? {
+        RecordId::Int(id) => Ok(id),
+        RecordId::Str(s) => s.parse().map_err(serde::de::Error::custom),
     }
 }
 
 impl Entry {
-    get_props! {
-        record_id, usize;
-        cmiles, String;
-        inchi_key, String;
+    /// Extract an entry from its Python counterpart, filling in `server`
+    /// since that is not stored on the Python object.
+    fn extract(ob: &PyAny, server: &str) -> PyResult<Self> {
+        Ok(Self {
+            kind: ob.getattr("type")?.extract()?,
+            record_id: ob.getattr("record_id")?.extract()?,
+            cmiles: ob.getattr("cmiles")?.extract()?,
+            inchi_key: ob.getattr("inchi_key")?.extract()?,
+            server: server.to_owned(),
+        })
+    }
+}
+
+/// Convert to the QCSubmit result class matching [Entry::kind].
+/// [Entry::server] is not part of the Python object.
+impl IntoPy<Py<PyAny>> for Entry {
+    fn into_py(self, py: Python<'_>) -> Py<PyAny> {
+        let kwargs = [
+            ("record_id", self.record_id.into_py(py)),
+            ("cmiles", self.cmiles.into_py(py)),
+            ("inchi_key", self.inchi_key.into_py(py)),
+        ]
+        .into_py_dict(py);
+        import_attr(py, PYMODULE, self.kind.python_class())
+            .unwrap()
+            .call((), Some(kwargs))
+            .unwrap()
+            .into()
     }
 }
 
@@ -53,8 +167,7 @@ macro_rules! result_collection {
 
         fn parse_file(filename: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
             Python::with_gil(|py| {
-                let m = PyModule::import(py, "openff.qcsubmit.results").unwrap();
-                Ok(m.getattr(stringify!($name))?
+                Ok(import_attr(py, PYMODULE, stringify!($name))?
                     .call_method1("parse_file", (filename.as_ref(),))?
                     .extract()?)
             })
@@ -62,11 +175,30 @@ macro_rules! result_collection {
 
         fn entries(&self) -> HashMap<String, Vec<Entry>> {
             Python::with_gil(|py| {
-                self.0
-                .getattr(py, "entries")
-                .unwrap()
-                .extract(py)
+                let entries: HashMap<String, Vec<&PyAny>> = self.0
+                .as_ref(py)
+                .getattr("entries")
                 .unwrap()
+                .extract()
+                .unwrap();
+                entries
+                .into_iter()
+                .map(|(server, entries)| {
+                    let entries = entries
+                    .into_iter()
+                    .map(|e| Entry::extract(e, &server).unwrap())
+                    .collect();
+                    (server, entries)
+                })
+                .collect()
+            })
+        }
+
+        fn set_entries(&mut self, entries: HashMap<String, Vec<Entry>>) {
+            Python::with_gil(|py| {
+                self.0
+                .setattr(py, "entries", entries.into_py(py))
+                .unwrap();
             })
         }
 
@@ -79,6 +211,29 @@ macro_rules! result_collection {
                 .unwrap()
             })
         }
+
+        fn record_filter(&mut self, name: &str, params: impl IntoPy<Py<PyAny>>) {
+            Python::with_gil(|py| {
+                let provenance = self.0.getattr(py, "provenance").unwrap();
+                let provenance: &PyDict = provenance.downcast(py).unwrap();
+                let filters = match provenance
+                    .get_item(native::APPLIED_FILTERS_KEY)
+                    .unwrap()
+                {
+                    Some(filters) => filters.downcast::<PyDict>().unwrap(),
+                    None => {
+                        let filters = PyDict::new(py);
+                        provenance
+                            .set_item(native::APPLIED_FILTERS_KEY, filters)
+                            .unwrap();
+                        filters
+                    }
+                };
+                filters
+                    .set_item(format!("{name}-{}", filters.len()), params.into_py(py))
+                    .unwrap();
+            })
+        }
     }
 
     impl $name {
@@ -88,11 +243,10 @@ macro_rules! result_collection {
             spec_name: &str,
         ) -> Self {
             Python::with_gil(|py| {
-                let m = PyModule::import(py, PYMODULE).unwrap();
                 let kwargs = [("client", client.into_py(py))].into_py_dict(py);
                 kwargs.set_item("datasets", datasets).unwrap();
                 kwargs.set_item("spec_name", spec_name).unwrap();
-                m.getattr(stringify!($name))
+                import_attr(py, PYMODULE, stringify!($name))
                 .unwrap()
                 .call_method("from_server", (), Some(kwargs))
                 .unwrap()
@@ -116,10 +270,92 @@ macro_rules! result_collection {
             })
         }
 
-        pub fn set_entries(&mut self, entries: HashMap<String, Vec<Entry>>) {
+        /// Serialize `self` in the canonical form described in
+        /// [native::ResultCollection::canonical_json].
+        pub fn canonical_json(&self) -> String {
+            self.to_native().canonical_json()
+        }
+
+        /// Return summary statistics for `self`, returning an error if the
+        /// `cmiles` of any entry cannot be parsed.
+        pub fn summary(&self) -> Result<summary::Summary, String> {
+            summary::Summary::new(&self.to_native())
+        }
+
+        /// Convert `self` to a [native::ResultCollection] through its JSON
+        /// representation.
+        pub fn to_native(&self) -> native::ResultCollection {
+            self.json(2).parse().unwrap()
+        }
+
+        /// Build a Python collection from `collection`, returning an error if
+        /// the type of `collection` does not match.
+        pub fn from_native(
+            collection: &native::ResultCollection,
+        ) -> Result<Self, Box<dyn Error>> {
+            Python::with_gil(|py| {
+                Ok(import_attr(py, PYMODULE, stringify!($name))?
+                    .call_method1("parse_raw", (collection.json(0),))?
+                    .extract()?)
+            })
+        }
+
+        /// Return the number of entries in `self`.
+        pub fn n_results(&self) -> usize {
+            self.entries().values().map(Vec::len).sum()
+        }
+
+        /// Return the number of distinct molecules in `self`, as identified
+        /// by their InChIKeys.
+        pub fn n_molecules(&self) -> usize {
+            self.to_native().n_molecules()
+        }
+
+        /// See [native::ResultCollection::union].
+        pub fn union(&self, other: &Self) -> Self {
+            let native = self.to_native().union(&other.to_native()).unwrap();
+            Self::from_native(&native).unwrap()
+        }
+
+        /// See [native::ResultCollection::intersection].
+        pub fn intersection(&self, other: &Self) -> Self {
+            let native =
+                self.to_native().intersection(&other.to_native()).unwrap();
+            Self::from_native(&native).unwrap()
+        }
+
+        /// See [native::ResultCollection::difference].
+        pub fn difference(&self, other: &Self) -> Self {
+            let native =
+                self.to_native().difference(&other.to_native()).unwrap();
+            Self::from_native(&native).unwrap()
+        }
+
+        /// See [native::ResultCollection::partition].
+        pub fn partition(
+            &self,
+            pred: impl FnMut(&Entry) -> bool,
+        ) -> (Self, Self) {
+            let (yes, no) = self.to_native().partition(pred);
+            (Self::from_native(&yes).unwrap(), Self::from_native(&no).unwrap())
+        }
+
+        /// Report the entries added and removed in going from `self` to the
+        /// newer version `other`.
+        pub fn diff(&self, other: &Self) -> native::CollectionDiff {
+            self.to_native().diff(&other.to_native())
+        }
+
+        /// Record the software versions in `provenance` in the `provenance`
+        /// block of `self` under [SOFTWARE_KEY], replacing any versions
+        /// recorded before. Call this before [Self::json] to keep track of
+        /// what produced the written collection.
+        pub fn record_provenance(&mut self, provenance: &Provenance) {
             Python::with_gil(|py| {
                 self.0
-                .setattr(py, "entries", entries.into_py(py))
+                .getattr(py, "provenance")
+                .unwrap()
+                .call_method1(py, "__setitem__", (SOFTWARE_KEY, provenance))
                 .unwrap();
             })
         }
@@ -137,11 +373,13 @@ macro_rules! result_collection {
 }
 
 result_collection! {
-    OptimizationResultCollection => TorsiondriveRecord,
+    BasicResultCollection => SinglepointRecord,
+    OptimizationResultCollection => OptimizationRecord,
     TorsionDriveResultCollection => TorsiondriveRecord,
 }
 
 into_py! {
+    BasicResultCollection,
     OptimizationResultCollection,
     TorsionDriveResultCollection,
 }
@@ -157,4 +395,232 @@ mod tests {
                 .unwrap();
         ds.to_records();
     }
+
+    #[test]
+    fn td_record_contents() {
+        let ds =
+            TorsionDriveResultCollection::parse_file("../testfiles/td.json")
+                .unwrap();
+        let (record, molecule) = ds.to_records().swap_remove(0);
+        let spec = &record.specification;
+        assert_eq!(spec.keywords.grid_spacing, vec![15]);
+        assert_eq!(spec.qc_specification().program, "psi4");
+        assert_eq!(spec.qc_specification().method, "b3lyp-d3bj");
+        assert_eq!(spec.qc_specification().basis.as_deref(), Some("dzvp"));
+
+        let energies = record.final_energies();
+        assert_eq!(energies.len(), 24);
+        assert!(energies.keys().all(|point| point.len() == 1));
+        let geometries = record
+            .optimized_geometries(&molecule.to_mapped_smiles())
+            .unwrap();
+        assert!(geometries.keys().eq(energies.keys()));
+        let geometry = geometries.values().next().unwrap();
+        assert_eq!(geometry.values.shape(), &[molecule.n_atoms(), 3]);
+        assert_eq!(geometry.unit.to_string(), "angstrom ** 1");
+    }
+
+    #[test]
+    fn get_opt_records() {
+        let ds = OptimizationResultCollection::parse_file(
+            "../testfiles/download_opt_want.json",
+        )
+        .unwrap();
+        let want: Vec<usize> = ds
+            .entries()
+            .values()
+            .flatten()
+            .map(|e| e.record_id)
+            .collect();
+        let got: Vec<usize> =
+            ds.to_records().iter().map(|(r, _)| r.id).collect();
+        assert_eq!(got.len(), want.len());
+        assert!(got.iter().all(|id| want.contains(id)));
+    }
+
+    #[test]
+    fn opt_record_contents() {
+        let ds = OptimizationResultCollection::parse_file(
+            "../testfiles/download_opt_want.json",
+        )
+        .unwrap();
+        let entries = ds.entries();
+        let entry = &entries.values().next().unwrap()[0];
+        let (record, molecule) = ds
+            .to_records()
+            .into_iter()
+            .find(|(r, _)| r.id == entry.record_id)
+            .unwrap();
+        assert!(record.is_converged());
+        assert_eq!(record.specification.program, "geometric");
+        assert_eq!(record.specification.qc_specification.program, "psi4");
+        let energies = record.energies.as_ref().unwrap();
+        assert_eq!(
+            record.final_energy().unwrap().magnitude(),
+            *energies.last().unwrap()
+        );
+        assert_eq!(record.trajectory().len(), energies.len());
+
+        let initial = record.initial_geometry(&entry.cmiles).unwrap();
+        let last = record.final_geometry(&entry.cmiles).unwrap().unwrap();
+        assert_eq!(last.values.shape(), &[molecule.n_atoms(), 3]);
+        assert_eq!(initial.unit.to_string(), "angstrom ** 1");
+        assert_ne!(initial.values, last.values);
+    }
+
+    #[test]
+    fn entry_serde() {
+        type Entries = HashMap<String, Vec<Entry>>;
+        let parse = |path| -> Entries {
+            let json: serde_json::Value =
+                serde_json::from_str(&std::fs::read_to_string(path).unwrap())
+                    .unwrap();
+            serde_json::from_value(json["entries"].clone()).unwrap()
+        };
+        // string record IDs
+        let td = parse("../testfiles/td.json");
+        let entry = &td["https://api.qcarchive.molssi.org:443/"][0];
+        assert_eq!(entry.kind, EntryType::Torsion);
+        assert_eq!(entry.record_id, 18535805);
+        assert_eq!(entry.inchi_key, "VEEYKGRLIXCNCZ-UHFFFAOYNA-N");
+        let json = serde_json::to_string(entry).unwrap();
+        assert!(json.starts_with(r#"{"type":"torsion","record_id":18535805,"#));
+
+        let opt = parse("../testfiles/download_opt_want.json");
+        let entries = &opt["https://api.qcarchive.molssi.org:443/"];
+        assert_eq!(entries.len(), 400);
+        let round_trip: Vec<Entry> =
+            serde_json::from_str(&serde_json::to_string(entries).unwrap())
+                .unwrap();
+        assert_eq!(&round_trip, entries);
+    }
+
+    #[test]
+    fn set_entries_round_trip() {
+        let mut ds = OptimizationResultCollection::parse_file(
+            "../testfiles/download_opt_want.json",
+        )
+        .unwrap();
+        let want = ds.json(2);
+        let entries = ds.entries();
+        let server = "https://api.qcarchive.molssi.org:443/";
+        assert!(entries[server].iter().all(|e| e.server == server));
+        ds.set_entries(entries.clone());
+        assert_eq!(ds.entries(), entries);
+        assert_eq!(ds.json(2), want);
+    }
+
+    #[test]
+    fn native_round_trip() {
+        let ds =
+            TorsionDriveResultCollection::parse_file("../testfiles/td.json")
+                .unwrap();
+        let native = ds.to_native();
+        assert_eq!(native.json(2), ds.json(2));
+        let back = TorsionDriveResultCollection::from_native(&native).unwrap();
+        assert_eq!(back.json(2), ds.json(2));
+        assert!(OptimizationResultCollection::from_native(&native).is_err());
+    }
+
+    #[test]
+    fn set_operations() {
+        let opt = OptimizationResultCollection::parse_file(
+            "../testfiles/download_opt_want.json",
+        )
+        .unwrap();
+        let filtered = OptimizationResultCollection::parse_file(
+            "../testfiles/filters_no_charge_want.json",
+        )
+        .unwrap();
+        assert_eq!(opt.n_results(), 400);
+        let inter = opt.intersection(&filtered);
+        assert_eq!(inter.json(2), filtered.json(2));
+        let (kept, dropped) = opt.partition(|e| {
+            filtered.entries().values().flatten().any(|f| f == e)
+        });
+        assert_eq!(kept.n_results(), filtered.n_results());
+        assert_eq!(opt.difference(&filtered).json(2), dropped.json(2));
+        assert_eq!(opt.diff(&filtered).removed.len(), dropped.n_results());
+        assert_eq!(dropped.union(&kept).n_results(), 400);
+    }
+
+    #[test]
+    fn charge_check_filter() {
+        let ds =
+            TorsionDriveResultCollection::parse_file("../testfiles/td.json")
+                .unwrap();
+        let count = |ds: &TorsionDriveResultCollection| -> usize {
+            ds.entries().values().map(Vec::len).sum()
+        };
+        let want = count(&ds);
+        let ds = ds
+            .filter(&[Box::new(filters::ChargeCheckFilter::new("gasteiger"))]);
+        assert_eq!(count(&ds), want);
+        let applied = Python::with_gil(|py| -> Vec<String> {
+            ds.0.getattr(py, "provenance")
+                .unwrap()
+                .call_method1(py, "get", (native::APPLIED_FILTERS_KEY,))
+                .unwrap()
+                .call_method0(py, "keys")
+                .unwrap()
+                .extract::<&PyAny>(py)
+                .unwrap()
+                .iter()
+                .unwrap()
+                .map(|k| k.unwrap().extract().unwrap())
+                .collect()
+        });
+        assert!(applied.iter().any(|k| k.starts_with("ChargeCheckFilter-")));
+    }
+
+    #[test]
+    fn id_filters() {
+        let ds =
+            TorsionDriveResultCollection::parse_file("../testfiles/td.json")
+                .unwrap();
+        let entries: Vec<_> = ds.entries().into_values().flatten().collect();
+        let first = &entries[0];
+        let ds = ds.filter(&[
+            Box::new(filters::InChIKeyFilter::include([&first.inchi_key])),
+            Box::new(filters::RecordIDFilter::exclude([first.record_id])),
+        ]);
+        let want = entries[1..]
+            .iter()
+            .filter(|e| e.inchi_key == first.inchi_key)
+            .count();
+        assert_eq!(ds.n_results(), want);
+    }
+
+    #[test]
+    fn hydrogen_bond_filter() {
+        let ds =
+            TorsionDriveResultCollection::parse_file("../testfiles/td.json")
+                .unwrap();
+        let want = ds.n_results();
+        let ds = ds.filter(&[
+            Box::new(filters::HydrogenBondFilter::default()),
+            Box::new(filters::UnperceivableStereoFilter::with_toolkits(vec![
+                "rdkit",
+            ])),
+        ]);
+        // td.json has already been through both of these
+        assert_eq!(ds.n_results(), want);
+        let filters = ds.to_native().applied_filters().unwrap().clone();
+        assert!(filters.values().any(|f| f["method"] == "baker-hubbard"));
+    }
+
+    #[test]
+    fn record_provenance() {
+        let mut ds =
+            TorsionDriveResultCollection::parse_file("../testfiles/td.json")
+                .unwrap();
+        let provenance = Provenance::gather();
+        ds.record_provenance(&provenance);
+        let json = ds.json(2);
+        assert!(json.contains("\"applied-filters\""));
+        assert!(json.contains(&format!(
+            "\"{SOFTWARE_KEY}\": {{\n      \"coprelos\": \"{}\"",
+            provenance.coprelos()
+        )));
+    }
 }
@@ -0,0 +1,684 @@
+//! A pure-Rust model of the JSON files written by QCSubmit result collections,
+//! for reading and writing collections without the Python stack. The output
+//! of [ResultCollection::json] matches the Python `json` method byte for byte,
+//! so collections can be passed back and forth between the two freely.
+
+use std::{
+    collections::HashSet, error::Error, fmt::Display, fs::read_to_string, io,
+    path::Path, str::FromStr,
+};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{
+    ser::{Formatter, PrettyFormatter},
+    Map, Value,
+};
+use utils::python_repr;
+
+use super::Entry;
+
+/// The key of the filters applied to a collection in its `provenance` block.
+pub const APPLIED_FILTERS_KEY: &str = "applied-filters";
+
+/// The kind of collection, serialized as its Python class name in the `type`
+/// field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CollectionType {
+    BasicResultCollection,
+    OptimizationResultCollection,
+    TorsionDriveResultCollection,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ResultCollection {
+    /// The entries of the collection keyed by server address, in the order
+    /// they appear in the file.
+    #[serde(deserialize_with = "entries")]
+    pub entries: IndexMap<String, Vec<Entry>>,
+    /// Free-form provenance, including the filters applied to the collection
+    /// under [APPLIED_FILTERS_KEY]. The order of the keys is preserved.
+    pub provenance: Map<String, Value>,
+    #[serde(rename = "type")]
+    pub kind: CollectionType,
+}
+
+/// The error returned when combining collections of different types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TypeMismatch(pub CollectionType, pub CollectionType);
+
+impl Display for TypeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot combine a {:?} with a {:?}", self.0, self.1)
+    }
+}
+
+impl Error for TypeMismatch {}
+
+/// The entries added to and removed from a collection between two versions,
+/// as returned by [ResultCollection::diff].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CollectionDiff {
+    pub added: Vec<Entry>,
+    pub removed: Vec<Entry>,
+}
+
+impl CollectionDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Format as a summary line followed by one line per entry, prefixed with
+/// `+` for added entries and `-` for removed ones.
+impl Display for CollectionDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} added, {} removed",
+            self.added.len(),
+            self.removed.len()
+        )?;
+        let lines = self
+            .added
+            .iter()
+            .map(|e| ('+', e))
+            .chain(self.removed.iter().map(|e| ('-', e)));
+        for (sign, e) in lines {
+            writeln!(
+                f,
+                "{sign} {} {} {} {}",
+                e.server, e.record_id, e.inchi_key, e.cmiles
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Deserialize the `entries` map, filling in [Entry::server] from the keys.
+fn entries<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<IndexMap<String, Vec<Entry>>, D::Error> {
+    let mut entries = IndexMap::<String, Vec<Entry>>::deserialize(d)?;
+    for (server, entries) in entries.iter_mut() {
+        for entry in entries {
+            entry.server.clone_from(server);
+        }
+    }
+    Ok(entries)
+}
+
+impl ResultCollection {
+    pub fn parse_file(
+        filename: impl AsRef<Path>,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(read_to_string(filename)?.parse()?)
+    }
+
+    /// Serialize `self` to JSON as Python's `json.dumps` does with `indent`,
+    /// which is what the `json` method of a Python collection produces.
+    pub fn json(&self, indent: usize) -> String {
+        let indent = vec![b' '; indent];
+        let mut out = Vec::new();
+        let mut ser = serde_json::Serializer::with_formatter(
+            &mut out,
+            PythonFormatter(PrettyFormatter::with_indent(&indent)),
+        );
+        self.serialize(&mut ser).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Put `self` in canonical order, so that equivalent collections
+    /// serialize identically no matter how they were built:
+    ///
+    /// - servers are sorted by address and entries by record ID
+    /// - provenance keys are sorted at every level, except for the applied
+    ///   filters, which stay in the order they were applied, as given by the
+    ///   index suffixed to each name
+    ///
+    /// Lists within the provenance, like the elements allowed by an
+    /// `ElementFilter`, are left alone since their order may be meaningful.
+    pub fn canonicalize(&mut self) {
+        self.entries.sort_keys();
+        for entries in self.entries.values_mut() {
+            entries.sort_by_key(|e| e.record_id);
+        }
+        self.provenance.sort_keys();
+        for (key, value) in self.provenance.iter_mut() {
+            value.sort_all_objects();
+            if key != APPLIED_FILTERS_KEY {
+                continue;
+            }
+            let Value::Object(filters) = value else {
+                continue;
+            };
+            let mut sorted: Vec<_> =
+                std::mem::take(filters).into_iter().collect();
+            sorted.sort_by(|(a, _), (b, _)| {
+                filter_order(a).cmp(&filter_order(b))
+            });
+            filters.extend(sorted);
+        }
+    }
+
+    /// Serialize a canonical copy of `self` (see
+    /// [ResultCollection::canonicalize]) as with `json(2)`, followed by a
+    /// newline. Floats are always formatted like Python's `repr`, so the
+    /// output is stable across reads and writes and diffs cleanly.
+    pub fn canonical_json(&self) -> String {
+        let mut canonical = self.clone();
+        canonical.canonicalize();
+        let mut json = canonical.json(2);
+        json.push('\n');
+        json
+    }
+
+    /// Return the filters applied to the collection and their parameters,
+    /// keyed by names like `RecordStatusFilter-1`.
+    pub fn applied_filters(&self) -> Option<&Map<String, Value>> {
+        self.provenance.get(APPLIED_FILTERS_KEY)?.as_object()
+    }
+
+    /// Return the number of entries in the collection.
+    pub fn n_results(&self) -> usize {
+        self.entries.values().map(Vec::len).sum()
+    }
+
+    /// Return the number of distinct molecules in the collection, as
+    /// identified by their InChIKeys.
+    pub fn n_molecules(&self) -> usize {
+        self.iter()
+            .map(|e| e.inchi_key.as_str())
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Iterate over the entries of every server in turn.
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.values().flatten()
+    }
+
+    fn check_type(&self, other: &Self) -> Result<(), TypeMismatch> {
+        if self.kind == other.kind {
+            Ok(())
+        } else {
+            Err(TypeMismatch(self.kind, other.kind))
+        }
+    }
+
+    /// Return a collection of the same type as `self` with `entries` and
+    /// `provenance`, dropping servers left without entries.
+    fn with_entries(
+        &self,
+        entries: impl IntoIterator<Item = Entry>,
+        provenance: Map<String, Value>,
+    ) -> Self {
+        let mut by_server: IndexMap<String, Vec<Entry>> = IndexMap::new();
+        for entry in entries {
+            by_server
+                .entry(entry.server.clone())
+                .or_default()
+                .push(entry);
+        }
+        Self {
+            entries: by_server,
+            provenance,
+            kind: self.kind,
+        }
+    }
+
+    /// Return the entries of `self` and `other`, without duplicate server and
+    /// record ID pairs. Only the filters applied to both collections still
+    /// hold for all of the entries, so only those are kept in the provenance.
+    pub fn union(&self, other: &Self) -> Result<Self, TypeMismatch> {
+        self.check_type(other)?;
+        let mut seen = HashSet::new();
+        let entries = self
+            .iter()
+            .chain(other.iter())
+            .filter(|e| seen.insert(key(e)))
+            .cloned();
+        let theirs = filter_list(&other.provenance);
+        let filters = filter_list(&self.provenance)
+            .into_iter()
+            .filter(|f| theirs.contains(f))
+            .collect();
+        Ok(self.with_entries(entries, merge_provenance(self, other, filters)))
+    }
+
+    /// Return the entries of `self` that are also in `other`. These passed
+    /// the filters applied to either collection, so the provenance includes
+    /// all of them.
+    pub fn intersection(&self, other: &Self) -> Result<Self, TypeMismatch> {
+        self.check_type(other)?;
+        let keep: HashSet<_> = other.iter().map(key).collect();
+        let entries = self.iter().filter(|e| keep.contains(&key(e))).cloned();
+        let mut filters = filter_list(&self.provenance);
+        for f in filter_list(&other.provenance) {
+            if !filters.contains(&f) {
+                filters.push(f);
+            }
+        }
+        Ok(self.with_entries(entries, merge_provenance(self, other, filters)))
+    }
+
+    /// Return the entries of `self` that are not in `other`, with the
+    /// provenance of `self`.
+    pub fn difference(&self, other: &Self) -> Result<Self, TypeMismatch> {
+        self.check_type(other)?;
+        let drop: HashSet<_> = other.iter().map(key).collect();
+        let entries = self.iter().filter(|e| !drop.contains(&key(e))).cloned();
+        Ok(self.with_entries(entries, self.provenance.clone()))
+    }
+
+    /// Split `self` into the entries for which `pred` returns true and those
+    /// for which it returns false. Both keep the provenance of `self`.
+    pub fn partition(
+        &self,
+        mut pred: impl FnMut(&Entry) -> bool,
+    ) -> (Self, Self) {
+        let (yes, no): (Vec<_>, Vec<_>) =
+            self.iter().cloned().partition(|e| pred(e));
+        (
+            self.with_entries(yes, self.provenance.clone()),
+            self.with_entries(no, self.provenance.clone()),
+        )
+    }
+
+    /// Report the entries added and removed in going from `self` to the newer
+    /// version `other`.
+    pub fn diff(&self, other: &Self) -> CollectionDiff {
+        let old: HashSet<_> = self.iter().map(key).collect();
+        let new: HashSet<_> = other.iter().map(key).collect();
+        CollectionDiff {
+            added: other
+                .iter()
+                .filter(|e| !old.contains(&key(e)))
+                .cloned()
+                .collect(),
+            removed: self
+                .iter()
+                .filter(|e| !new.contains(&key(e)))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// The identity of an entry's record across collections.
+fn key(entry: &Entry) -> (&str, usize) {
+    (&entry.server, entry.record_id)
+}
+
+/// Split an applied filter name like `RecordStatusFilter-1` into the filter's
+/// class name and its index.
+fn split_filter_name(name: &str) -> (&str, Option<usize>) {
+    match name.rsplit_once('-') {
+        Some((class, i)) => match i.parse() {
+            Ok(i) => (class, Some(i)),
+            Err(_) => (name, None),
+        },
+        None => (name, None),
+    }
+}
+
+/// Return the applied filters in `provenance` as (class name, parameters)
+/// pairs in the order they were applied.
+fn filter_list(provenance: &Map<String, Value>) -> Vec<(String, Value)> {
+    let Some(Value::Object(filters)) = provenance.get(APPLIED_FILTERS_KEY)
+    else {
+        return Vec::new();
+    };
+    let mut filters: Vec<_> = filters.iter().collect();
+    filters.sort_by(|(a, _), (b, _)| filter_order(a).cmp(&filter_order(b)));
+    filters
+        .into_iter()
+        .map(|(name, params)| {
+            (split_filter_name(name).0.to_owned(), params.clone())
+        })
+        .collect()
+}
+
+/// Combine the provenance of `a` and `b`, with `filters` numbered in order as
+/// the applied filters. For any other key present in both, the value from `a`
+/// is kept.
+fn merge_provenance(
+    a: &ResultCollection,
+    b: &ResultCollection,
+    filters: Vec<(String, Value)>,
+) -> Map<String, Value> {
+    let mut provenance = a.provenance.clone();
+    for (k, v) in &b.provenance {
+        if !provenance.contains_key(k) {
+            provenance.insert(k.clone(), v.clone());
+        }
+    }
+    if filters.is_empty() {
+        provenance.remove(APPLIED_FILTERS_KEY);
+    } else {
+        let filters = filters
+            .into_iter()
+            .enumerate()
+            .map(|(i, (class, params))| (format!("{class}-{i}"), params))
+            .collect();
+        provenance
+            .insert(APPLIED_FILTERS_KEY.to_owned(), Value::Object(filters));
+    }
+    provenance
+}
+
+/// Return the key for sorting the applied filter `name`, like
+/// `RecordStatusFilter-1`, by its index and then by name. Names without an
+/// index sort last.
+pub(super) fn filter_order(name: &str) -> (usize, &str) {
+    (split_filter_name(name).1.unwrap_or(usize::MAX), name)
+}
+
+impl FromStr for ResultCollection {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+/// A [Formatter] matching the output of Python's `json.dumps` with an
+/// indent: the same layout as [PrettyFormatter], but with floats formatted by
+/// `repr` and non-ASCII characters escaped as with `ensure_ascii=True`.
+struct PythonFormatter<'a>(PrettyFormatter<'a>);
+
+impl Formatter for PythonFormatter<'_> {
+    fn write_f64<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        value: f64,
+    ) -> io::Result<()> {
+        let s = match value {
+            v if v.is_nan() => "NaN".to_owned(),
+            f64::INFINITY => "Infinity".to_owned(),
+            f64::NEG_INFINITY => "-Infinity".to_owned(),
+            v => python_repr(v),
+        };
+        writer.write_all(s.as_bytes())
+    }
+
+    fn write_string_fragment<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        fragment: &str,
+    ) -> io::Result<()> {
+        for c in fragment.chars() {
+            if c < '\x7f' {
+                write!(writer, "{c}")?;
+            } else {
+                let mut buf = [0; 2];
+                for unit in c.encode_utf16(&mut buf) {
+                    write!(writer, "\\u{unit:04x}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn begin_array<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        self.0.begin_array(writer)
+    }
+
+    fn end_array<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        self.0.end_array(writer)
+    }
+
+    fn begin_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.0.begin_array_value(writer, first)
+    }
+
+    fn end_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        self.0.end_array_value(writer)
+    }
+
+    fn begin_object<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        self.0.begin_object(writer)
+    }
+
+    fn end_object<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        self.0.end_object(writer)
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.0.begin_object_key(writer, first)
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        self.0.begin_object_value(writer)
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        self.0.end_object_value(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for file in [
+            "download_opt_want.json",
+            "download_td_want.json",
+            "filters_no_charge_want.json",
+        ] {
+            let want = read_to_string(format!("../testfiles/{file}")).unwrap();
+            let got: ResultCollection = want.parse().unwrap();
+            assert_eq!(got.json(2), want, "{file}");
+        }
+    }
+
+    #[test]
+    fn read_td() {
+        let got = ResultCollection::parse_file("../testfiles/td.json").unwrap();
+        assert_eq!(got.kind, CollectionType::TorsionDriveResultCollection);
+        let server = "https://api.qcarchive.molssi.org:443/";
+        assert_eq!(got.entries[server].len(), 5);
+        assert!(got.entries[server].iter().all(|e| e.server == server));
+        let filters = got.applied_filters().unwrap();
+        assert_eq!(
+            filters["HydrogenBondFilter-0"]["method"],
+            Value::from("baker-hubbard")
+        );
+        // Python writes the string record IDs back out as integers
+        let json = got.json(2);
+        assert!(json.contains(r#""record_id": 18535805,"#));
+        assert!(json.contains(r#""tolerance": 1.2"#));
+    }
+
+    #[test]
+    fn canonical() {
+        let mut shuffled =
+            ResultCollection::parse_file("../testfiles/td.json").unwrap();
+        let want = shuffled.canonical_json();
+        assert!(want.ends_with("}\n"));
+
+        let server = "https://api.qcarchive.molssi.org:443/";
+        shuffled.entries[server].reverse();
+        shuffled
+            .entries
+            .insert("https://example.org/".to_owned(), Vec::new());
+        shuffled.entries.reverse();
+        let filters = shuffled.provenance[APPLIED_FILTERS_KEY]
+            .as_object_mut()
+            .unwrap();
+        filters.sort_keys();
+        filters.insert("ElementFilter-10".to_owned(), Value::Null);
+        shuffled
+            .provenance
+            .insert("software".to_owned(), serde_json::json!({"b": 1, "a": 2}));
+        shuffled.provenance = std::mem::take(&mut shuffled.provenance)
+            .into_iter()
+            .rev()
+            .collect();
+
+        let got = shuffled.canonical_json();
+        let got: ResultCollection = got.parse().unwrap();
+        let ids: Vec<_> =
+            got.entries[server].iter().map(|e| e.record_id).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        let servers: Vec<_> = got.entries.keys().collect();
+        assert_eq!(servers, [server, "https://example.org/"]);
+        let keys: Vec<_> = got.provenance.keys().collect();
+        assert_eq!(keys, [APPLIED_FILTERS_KEY, "software"]);
+        let names: Vec<_> = got.applied_filters().unwrap().keys().collect();
+        assert_eq!(
+            names,
+            [
+                "HydrogenBondFilter-0",
+                "RecordStatusFilter-1",
+                "ConnectivityFilter-2",
+                "UnperceivableStereoFilter-3",
+                "ElementFilter-4",
+                "ElementFilter-10",
+            ]
+        );
+        let software: Vec<_> = got.provenance["software"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect();
+        assert_eq!(software, ["a", "b"]);
+        assert_eq!(got.canonical_json(), shuffled.canonical_json());
+    }
+
+    #[test]
+    fn set_operations() {
+        let opt =
+            ResultCollection::parse_file("../testfiles/download_opt_want.json")
+                .unwrap();
+        let filtered = ResultCollection::parse_file(
+            "../testfiles/filters_no_charge_want.json",
+        )
+        .unwrap();
+        assert_eq!((opt.n_results(), filtered.n_results()), (400, 239));
+        assert!(opt.n_molecules() <= opt.n_results());
+
+        // the filtered collection is a subset of the unfiltered one
+        let union = opt.union(&filtered).unwrap();
+        assert_eq!(union.entries, opt.entries);
+        assert_eq!(union.applied_filters(), None);
+        let inter = opt.intersection(&filtered).unwrap();
+        assert_eq!(inter.n_results(), 239);
+        assert_eq!(inter.applied_filters(), filtered.applied_filters());
+        let rest = opt.difference(&filtered).unwrap();
+        assert_eq!(rest.n_results(), 400 - 239);
+        assert_eq!(rest.union(&inter).unwrap().n_results(), 400);
+
+        let diff = opt.diff(&filtered);
+        assert_eq!((diff.added.len(), diff.removed.len()), (0, 161));
+        assert!(diff.to_string().starts_with("0 added, 161 removed\n- "));
+        assert!(opt.diff(&opt).is_empty());
+
+        let (even, odd) = opt.partition(|e| e.record_id % 2 == 0);
+        assert_eq!(even.n_results() + odd.n_results(), 400);
+        assert!(odd.iter().all(|e| e.record_id % 2 == 1));
+        assert!(even.intersection(&odd).unwrap().entries.is_empty());
+
+        let td = ResultCollection::parse_file("../testfiles/td.json").unwrap();
+        assert_eq!(
+            opt.union(&td).unwrap_err(),
+            TypeMismatch(
+                CollectionType::OptimizationResultCollection,
+                CollectionType::TorsionDriveResultCollection
+            )
+        );
+    }
+
+    #[test]
+    fn merge_filters() {
+        let mut a =
+            ResultCollection::parse_file("../testfiles/td.json").unwrap();
+        let mut b = a.clone();
+        let filters =
+            b.provenance[APPLIED_FILTERS_KEY].as_object_mut().unwrap();
+        // same filters in a different order with a different index, plus one
+        // extra
+        let element = filters.shift_remove("ElementFilter-4").unwrap();
+        filters.insert("ElementFilter-0".to_owned(), element);
+        filters.shift_remove("HydrogenBondFilter-0");
+        let mut renumbered = Map::new();
+        for (i, (name, v)) in std::mem::take(filters).into_iter().enumerate() {
+            let class = split_filter_name(&name).0;
+            renumbered.insert(format!("{class}-{}", i + 1), v);
+        }
+        renumbered.insert(
+            "ChargeCheckFilter-9".to_owned(),
+            serde_json::json!({"method": "am1bccelf10"}),
+        );
+        *filters = renumbered;
+        a.provenance.insert("software".to_owned(), Value::from("a"));
+        b.provenance.insert("software".to_owned(), Value::from("b"));
+        b.provenance.insert("extra".to_owned(), Value::from(1));
+
+        let union = a.union(&b).unwrap();
+        let names: Vec<_> = union.applied_filters().unwrap().keys().collect();
+        assert_eq!(
+            names,
+            [
+                "RecordStatusFilter-0",
+                "ConnectivityFilter-1",
+                "UnperceivableStereoFilter-2",
+                "ElementFilter-3"
+            ]
+        );
+        assert_eq!(union.provenance["software"], Value::from("a"));
+        assert_eq!(union.provenance["extra"], Value::from(1));
+
+        let inter = a.intersection(&b).unwrap();
+        let names: Vec<_> = inter.applied_filters().unwrap().keys().collect();
+        assert_eq!(names.len(), 6);
+        assert_eq!(names[0], "HydrogenBondFilter-0");
+        assert_eq!(names[5], "ChargeCheckFilter-5");
+    }
+
+    #[test]
+    fn python_strings() {
+        let mut got =
+            ResultCollection::parse_file("../testfiles/td.json").unwrap();
+        got.provenance
+            .insert("note".to_owned(), Value::from("Å\u{1f600}\x7f"));
+        got.provenance.insert("small".to_owned(), Value::from(1e-5));
+        got.provenance
+            .insert("empty".to_owned(), Value::Object(Map::new()));
+        let json = got.json(2);
+        assert!(json.contains(r#""note": "\u00c5\ud83d\ude00\u007f""#));
+        assert!(json.contains(r#""small": 1e-05"#));
+        assert!(json.contains(r#""empty": {}"#));
+    }
+}
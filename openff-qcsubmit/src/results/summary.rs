@@ -0,0 +1,204 @@
+//! Summary statistics for a result collection, like the tables included in
+//! the pull requests for new datasets. Everything here is computed from the
+//! entries and provenance alone, parsing the `cmiles` of each entry natively,
+//! so no records are fetched from the server.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+};
+
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::Value;
+use utils::smiles::bracket_atoms;
+
+use super::native::{filter_order, ResultCollection, APPLIED_FILTERS_KEY};
+
+/// The width of the bins in [Summary::heavy_atoms].
+pub const HEAVY_ATOM_BIN: usize = 5;
+
+/// Summary statistics for a collection. The counts per element, formal charge,
+/// and number of heavy atoms are over unique molecules, as identified by their
+/// InChIKeys, rather than over entries.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Summary {
+    pub n_results: usize,
+    pub n_molecules: usize,
+    /// The number of entries from each server, in the order they appear in
+    /// the collection.
+    pub servers: IndexMap<String, usize>,
+    /// The number of molecules containing each element.
+    pub elements: BTreeMap<String, usize>,
+    /// The number of molecules with each total formal charge.
+    pub charges: BTreeMap<i32, usize>,
+    /// The number of molecules by heavy atom count, keyed by the start of
+    /// each bin of width [HEAVY_ATOM_BIN].
+    pub heavy_atoms: BTreeMap<usize, usize>,
+    /// The number of molecules with a given number of entries.
+    pub entries_per_molecule: BTreeMap<usize, usize>,
+    /// The filters applied to the collection and their parameters, keyed by
+    /// names like `RecordStatusFilter-1`, in the order they were applied.
+    pub filters: IndexMap<String, Value>,
+}
+
+impl Summary {
+    /// Summarize `collection`, returning an error if the `cmiles` of any of
+    /// its entries cannot be parsed.
+    pub fn new(collection: &ResultCollection) -> Result<Self, String> {
+        let mut servers = IndexMap::new();
+        // InChIKey => (cmiles of the first entry, number of entries)
+        let mut molecules: IndexMap<&str, (&str, usize)> = IndexMap::new();
+        for (server, entries) in &collection.entries {
+            *servers.entry(server.clone()).or_default() += entries.len();
+            for entry in entries {
+                molecules
+                    .entry(entry.inchi_key.as_str())
+                    .or_insert((&entry.cmiles, 0))
+                    .1 += 1;
+            }
+        }
+
+        let mut elements = BTreeMap::new();
+        let mut charges = BTreeMap::new();
+        let mut heavy_atoms = BTreeMap::new();
+        let mut entries_per_molecule = BTreeMap::new();
+        for (cmiles, count) in molecules.values() {
+            let atoms = bracket_atoms(cmiles)?;
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            for atom in &atoms {
+                *counts.entry(&atom.symbol).or_default() += 1;
+            }
+            for symbol in counts.keys() {
+                *elements.entry(symbol.to_string()).or_default() += 1;
+            }
+            let charge = atoms.iter().map(|a| a.charge).sum();
+            *charges.entry(charge).or_default() += 1;
+            let heavy = atoms.len() - counts.get("H").unwrap_or(&0);
+            *heavy_atoms
+                .entry(heavy / HEAVY_ATOM_BIN * HEAVY_ATOM_BIN)
+                .or_default() += 1;
+            *entries_per_molecule.entry(*count).or_default() += 1;
+        }
+
+        let mut filters: Vec<_> = collection
+            .applied_filters()
+            .into_iter()
+            .flatten()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        filters.sort_by(|(a, _), (b, _)| filter_order(a).cmp(&filter_order(b)));
+
+        Ok(Self {
+            n_results: collection.n_results(),
+            n_molecules: molecules.len(),
+            servers,
+            elements,
+            charges,
+            heavy_atoms,
+            entries_per_molecule,
+            filters: filters.into_iter().collect(),
+        })
+    }
+
+    /// Serialize `self` to pretty-printed JSON. Integer keys, like the
+    /// charges, are written as strings.
+    pub fn json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Render `self` as a series of Markdown tables, ending with a list of
+    /// the applied filters.
+    pub fn markdown(&self) -> String {
+        let mut out = String::new();
+        table(
+            &mut out,
+            ["Property", "Count"],
+            [
+                ("Results".to_owned(), self.n_results),
+                ("Unique molecules".to_owned(), self.n_molecules),
+            ],
+        );
+        table(&mut out, ["Server", "Results"], self.servers.clone());
+        table(&mut out, ["Element", "Molecules"], self.elements.clone());
+        table(
+            &mut out,
+            ["Formal charge", "Molecules"],
+            self.charges.iter().map(|(c, n)| (format!("{c:+}"), *n)),
+        );
+        table(
+            &mut out,
+            ["Heavy atoms", "Molecules"],
+            self.heavy_atoms.iter().map(|(start, n)| {
+                (format!("{start}-{}", start + HEAVY_ATOM_BIN - 1), *n)
+            }),
+        );
+        table(
+            &mut out,
+            ["Results per molecule", "Molecules"],
+            self.entries_per_molecule.clone(),
+        );
+
+        writeln!(out, "Applied filters ({APPLIED_FILTERS_KEY}):").unwrap();
+        if self.filters.is_empty() {
+            writeln!(out, "- none").unwrap();
+        }
+        for (name, params) in &self.filters {
+            writeln!(out, "- `{name}`: `{params}`").unwrap();
+        }
+        out
+    }
+}
+
+/// Append a two-column Markdown table with `header` and `rows` to `out`,
+/// followed by a blank line.
+fn table<K: ToString>(
+    out: &mut String,
+    header: [&str; 2],
+    rows: impl IntoIterator<Item = (K, usize)>,
+) {
+    writeln!(out, "| {} | {} |", header[0], header[1]).unwrap();
+    writeln!(out, "|---|---:|").unwrap();
+    for (k, n) in rows {
+        writeln!(out, "| {} | {n} |", k.to_string()).unwrap();
+    }
+    writeln!(out).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarize_td() {
+        let td = ResultCollection::parse_file("../testfiles/td.json").unwrap();
+        let summary = Summary::new(&td).unwrap();
+        assert_eq!(summary.n_results, td.n_results());
+        assert_eq!(summary.n_molecules, td.n_molecules());
+        assert_eq!(summary.servers.values().sum::<usize>(), td.n_results());
+        assert_eq!(
+            summary
+                .entries_per_molecule
+                .iter()
+                .map(|(k, v)| k * v)
+                .sum::<usize>(),
+            td.n_results()
+        );
+        for counts in [
+            summary.charges.values().sum::<usize>(),
+            summary.heavy_atoms.values().sum(),
+        ] {
+            assert_eq!(counts, td.n_molecules());
+        }
+        assert_eq!(summary.elements["H"], td.n_molecules());
+        assert_eq!(summary.filters.len(), td.applied_filters().unwrap().len());
+
+        let md = summary.markdown();
+        assert!(md.contains(&format!("| Results | {} |", td.n_results())));
+        for name in summary.filters.keys() {
+            assert!(md.contains(&format!("- `{name}`")));
+        }
+        let json: Value = serde_json::from_str(&summary.json()).unwrap();
+        assert_eq!(json["n_molecules"], td.n_molecules());
+    }
+}
@@ -0,0 +1,101 @@
+//! Compare `Parameter::k` and [ForceField::label_molecules], which call their
+//! Python helpers through the cache in `utils::cache`, against calling the
+//! same helpers after compiling them with `PyModule::from_code` every time, as
+//! the wrappers did before the cache. Run with `cargo bench -p openff-toolkit`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use openff_toolkit::{ForceField, Molecule};
+use pyo3::{types::PyModule, PyAny, Python};
+
+const FORCE_FIELD: &str = "openff-2.1.0.offxml";
+
+const SMILES: &str = "[H:5][C:1]([H:6])([H:7])[C:2]([H:8])([H:9])[O:3][H:4]";
+
+const PARAMETERS: &str =
+    include_str!("../src/typing/engines/smirnoff/parameters.py");
+
+const FORCEFIELD: &str =
+    include_str!("../src/typing/engines/smirnoff/forcefield.py");
+
+/// Load [FORCE_FIELD] directly in Python, for the uncached benchmarks.
+fn py_force_field(py: Python<'_>) -> &PyAny {
+    PyModule::import(py, "openff.toolkit")
+        .unwrap()
+        .getattr("ForceField")
+        .unwrap()
+        .call1((FORCE_FIELD,))
+        .unwrap()
+}
+
+/// Compile `code` and return its function `name`.
+fn compile<'py>(py: Python<'py>, code: &str, name: &str) -> &'py PyAny {
+    PyModule::from_code(py, code, "", "")
+        .unwrap()
+        .getattr(name)
+        .unwrap()
+}
+
+fn k(c: &mut Criterion) {
+    let mut group = c.benchmark_group("k");
+    Python::with_gil(|py| {
+        let params: Vec<&PyAny> = py_force_field(py)
+            .call_method1("get_parameter_handler", ("Bonds",))
+            .unwrap()
+            .getattr("parameters")
+            .unwrap()
+            .extract()
+            .unwrap();
+        group.bench_function("uncached", |b| {
+            b.iter(|| {
+                for &p in &params {
+                    compile(py, PARAMETERS, "get_k").call1((p,)).unwrap();
+                }
+            })
+        });
+    });
+    let ff = ForceField::load(FORCE_FIELD).unwrap();
+    let params = ff.get_parameter_handler("Bonds").unwrap().parameters();
+    group.bench_function("cached", |b| {
+        b.iter(|| {
+            for p in &params {
+                p.k();
+            }
+        })
+    });
+    group.finish();
+}
+
+fn label_molecules(c: &mut Criterion) {
+    let mut group = c.benchmark_group("label_molecules");
+    Python::with_gil(|py| {
+        let ff = py_force_field(py);
+        let top = PyModule::import(py, "openff.toolkit")
+            .unwrap()
+            .getattr("Molecule")
+            .unwrap()
+            .call_method1("from_mapped_smiles", (SMILES,))
+            .unwrap()
+            .call_method0("to_topology")
+            .unwrap();
+        group.bench_function("uncached", |b| {
+            b.iter(|| {
+                compile(py, FORCEFIELD, "label_molecules")
+                    .call1((ff, top))
+                    .unwrap()
+            })
+        });
+    });
+    let ff = ForceField::load(FORCE_FIELD).unwrap();
+    let mol = Molecule::from_mapped_smiles(SMILES).unwrap();
+    group.bench_function("cached", |b| {
+        b.iter_batched(
+            || mol.to_topology(),
+            |top| ff.label_molecules(top),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, k, label_molecules);
+criterion_main!(benches);
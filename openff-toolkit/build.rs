@@ -1,12 +1,9 @@
-use std::env;
+//! Set the runtime search path for libpython. See `utils/rpath.rs`.
+
+#[path = "../utils/rpath.rs"]
+mod rpath;
 
 fn main() {
-    println!("cargo:rerun-if-env-changed=CONDA_PREFIX");
-    let Ok(prefix) = env::var("CONDA_PREFIX") else {
-        eprintln!(
-            "must build inside a conda environment containing openff-toolkit"
-        );
-        std::process::exit(1);
-    };
-    println!("cargo:rustc-env=LD_LIBRARY_PATH={prefix}/lib");
+    println!("cargo:rerun-if-changed=build.rs");
+    rpath::link_python();
 }
@@ -1,9 +1,14 @@
-use openff_units::Quantity;
+use openff_units::{
+    array::{ArrayBuffer, ArrayQuantity},
+    native::Unit,
+    unit, Quantity,
+};
 use pyo3::{
-    types::{IntoPyDict, PyModule},
-    FromPyObject, IntoPy, Py, PyAny, Python,
+    types::IntoPyDict, FromPyObject, IntoPy, Py, PyAny, PyResult, Python,
 };
 
+use utils::{get_props, import_attr};
+
 use crate::Topology;
 
 const PYMODULE: &str = "openff.toolkit.topology.molecule";
@@ -26,12 +31,27 @@ impl Default for Molecule {
 impl Molecule {
     pub fn new() -> Self {
         Python::with_gil(|py| {
-            let m = PyModule::import(py, PYMODULE).unwrap();
-            let top = m.getattr("Molecule").unwrap();
+            let top = import_attr(py, PYMODULE, "Molecule").unwrap();
             top.call0().unwrap().extract().unwrap()
         })
     }
 
+    /// Construct a Molecule from a SMILES string with an atom map entry for
+    /// every atom, such as the `cmiles` in a QCSubmit result collection.
+    /// Undefined stereochemistry is allowed.
+    pub fn from_mapped_smiles(mapped_smiles: &str) -> PyResult<Self> {
+        Python::with_gil(|py| {
+            let kwargs = [("allow_undefined_stereo", true)].into_py_dict(py);
+            import_attr(py, PYMODULE, "Molecule")?
+                .call_method(
+                    "from_mapped_smiles",
+                    (mapped_smiles,),
+                    Some(kwargs),
+                )?
+                .extract()
+        })
+    }
+
     pub fn add_atom(
         &mut self,
         atomic_number: u8,
@@ -81,6 +101,96 @@ impl Molecule {
         })
     }
 
+    /// Return the partial charges of `self` in units of elementary charge, if
+    /// they have been assigned. The conversion is done by pint, so this works
+    /// for charges in any unit pint knows, not only those in
+    /// [openff_units::native].
+    pub fn partial_charges(&self) -> PyResult<Option<Vec<f64>>> {
+        let Some(charges) = self.partial_charges_quantity()? else {
+            return Ok(None);
+        };
+        let e = unit().get("elementary_charge")?;
+        let charges = ArrayQuantity::from_quantity(&charges.to(&e)?)?;
+        Ok(Some(charges.values.into_raw_vec()))
+    }
+
+    /// Return the partial charges of `self` as an array of shape `(n_atoms,)`,
+    /// if they have been assigned. Returns an error if their units are not
+    /// supported by [openff_units::native].
+    pub fn partial_charges_array(&self) -> PyResult<Option<ArrayQuantity>> {
+        self.partial_charges_quantity()?
+            .map(|charges| ArrayQuantity::from_quantity(&charges))
+            .transpose()
+    }
+
+    fn partial_charges_quantity(&self) -> PyResult<Option<Quantity>> {
+        Python::with_gil(|py| {
+            self.0.getattr(py, "partial_charges")?.extract(py)
+        })
+    }
+
+    /// Return the conformers of `self`, each an array of shape `(n_atoms, 3)`.
+    /// Returns an error if their units are not supported by
+    /// [openff_units::native].
+    pub fn conformers(&self) -> PyResult<Vec<ArrayQuantity>> {
+        Python::with_gil(|py| {
+            let conformers: Option<Vec<Quantity>> =
+                self.0.getattr(py, "conformers")?.extract(py)?;
+            conformers
+                .unwrap_or_default()
+                .iter()
+                .map(ArrayQuantity::from_quantity)
+                .collect()
+        })
+    }
+
+    /// Return the magnitude of conformer `index` as a buffer that can be
+    /// viewed without copying it, along with its units. See
+    /// [ArrayQuantity::buffer].
+    pub fn conformer_buffer(
+        &self,
+        index: usize,
+    ) -> PyResult<(ArrayBuffer, Unit)> {
+        Python::with_gil(|py| {
+            let conformer: Quantity = self
+                .0
+                .getattr(py, "conformers")?
+                .as_ref(py)
+                .get_item(index)?
+                .extract()?;
+            ArrayQuantity::buffer(&conformer)
+        })
+    }
+
+    /// Add `conformer`, an array of shape `(n_atoms, 3)` with units of
+    /// length, to `self`, returning the index of the new conformer.
+    pub fn add_conformer(
+        &mut self,
+        conformer: &ArrayQuantity,
+    ) -> PyResult<usize> {
+        Python::with_gil(|py| {
+            self.0
+                .call_method1(py, "add_conformer", (conformer.to_quantity()?,))?
+                .extract(py)
+        })
+    }
+
+    /// Assign partial charges to `self` with the toolkit method named by
+    /// `partial_charge_method`, such as `am1bcc` or `am1bccelf10`.
+    pub fn assign_partial_charges(
+        &mut self,
+        partial_charge_method: &str,
+    ) -> PyResult<()> {
+        Python::with_gil(|py| {
+            self.0.call_method1(
+                py,
+                "assign_partial_charges",
+                (partial_charge_method,),
+            )?;
+            Ok(())
+        })
+    }
+
     /// Return a SMILES representation of `self`, optionally in isomeric,
     /// explicit-hydrogen, and mapped form. In Python, the default values are
     /// `isomeric=True`, `explicit_hydrogens=True`, and `mapped=False`. For
@@ -114,7 +224,18 @@ impl Molecule {
         })
     }
 
+    get_props! {
+        n_atoms, usize;
+        n_conformers, usize;
+    }
+
     pub fn to_smiles_default(&self) -> String {
         self.to_smiles(true, true, false)
     }
+
+    /// Return the mapped SMILES representation of `self`, where the map index
+    /// of each atom is one more than its index in `self`.
+    pub fn to_mapped_smiles(&self) -> String {
+        self.to_smiles(true, true, true)
+    }
 }
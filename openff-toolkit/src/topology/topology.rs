@@ -1,8 +1,8 @@
+use openff_units::array::ArrayQuantity;
 use pyo3::{
-    types::{PyIterator, PyModule},
-    FromPyObject, Py, PyAny, PyObject, Python,
+    types::PyIterator, FromPyObject, Py, PyAny, PyObject, PyResult, Python,
 };
-use utils::get_props;
+use utils::{get_props, import_attr};
 
 use crate::Molecule;
 
@@ -22,16 +22,14 @@ impl Default for Topology {
 impl Topology {
     pub fn new() -> Self {
         Python::with_gil(|py| {
-            let m = PyModule::import(py, PYMODULE).unwrap();
-            let top = m.getattr("Topology").unwrap();
+            let top = import_attr(py, PYMODULE, "Topology").unwrap();
             top.call0().unwrap().extract().unwrap()
         })
     }
 
     pub fn from_molecules(molecules: Vec<Molecule>) -> Self {
         Python::with_gil(|py| {
-            let m = PyModule::import(py, PYMODULE).unwrap();
-            let top = m.getattr("Topology").unwrap();
+            let top = import_attr(py, PYMODULE, "Topology").unwrap();
             top.call_method1("from_molecules", (molecules,))
                 .unwrap()
                 .extract()
@@ -53,7 +51,35 @@ impl Topology {
         })
     }
 
+    /// Return the positions of every atom in `self` as an array of shape
+    /// `(n_atoms, 3)`, taken from the first conformer of each molecule, or
+    /// `None` if any molecule has no conformers.
+    pub fn get_positions(&self) -> PyResult<Option<ArrayQuantity>> {
+        Python::with_gil(|py| {
+            let positions = self.0.call_method0(py, "get_positions")?;
+            if positions.is_none(py) {
+                return Ok(None);
+            }
+            let positions = positions.extract(py)?;
+            Ok(Some(ArrayQuantity::from_quantity(&positions)?))
+        })
+    }
+
+    /// Set the positions of every atom in `self` from an array of shape
+    /// `(n_atoms, 3)` with units of length.
+    pub fn set_positions(&mut self, positions: &ArrayQuantity) -> PyResult<()> {
+        Python::with_gil(|py| {
+            self.0.call_method1(
+                py,
+                "set_positions",
+                (positions.to_quantity()?,),
+            )?;
+            Ok(())
+        })
+    }
+
     get_props! {
+        n_atoms, usize;
         n_unique_molecules, usize;
         n_molecules, usize;
     }
@@ -61,7 +87,7 @@ impl Topology {
 
 #[cfg(test)]
 mod tests {
-    use openff_units::{unit, Quantity};
+    use openff_units::{array::ndarray::Array, unit, Quantity};
 
     use super::*;
 
@@ -99,4 +125,44 @@ mod tests {
         assert_eq!(top.n_unique_molecules(), 1);
         assert_eq!(top.n_molecules(), 2);
     }
+
+    #[test]
+    fn positions() {
+        let angstrom = "angstrom".parse().unwrap();
+        let mut ethanol = create_ethanol();
+        let conformer = ArrayQuantity::new(
+            Array::linspace(0.0, 2.6, 27)
+                .into_shape((9, 3))
+                .unwrap()
+                .into_dyn(),
+            "angstrom".parse().unwrap(),
+        );
+        assert_eq!(ethanol.add_conformer(&conformer).unwrap(), 0);
+        assert_eq!(ethanol.conformers().unwrap(), vec![conformer.clone()]);
+        let (buf, _) = ethanol.conformer_buffer(0).unwrap();
+        assert_eq!(buf.view().unwrap(), conformer.values.view());
+
+        let mut top = Topology::from_molecules(vec![ethanol]);
+        let got = top
+            .get_positions()
+            .unwrap()
+            .unwrap()
+            .m_as(&angstrom)
+            .unwrap();
+        assert!((&got - &conformer.values).iter().all(|d| d.abs() < 1e-12));
+
+        let moved = &conformer.values + 1.0;
+        top.set_positions(&ArrayQuantity::new(moved.clone(), angstrom.clone()))
+            .unwrap();
+        let got = top
+            .get_positions()
+            .unwrap()
+            .unwrap()
+            .m_as(&angstrom)
+            .unwrap();
+        assert!((&got - &moved).iter().all(|d| d.abs() < 1e-12));
+
+        let top = Topology::from_molecules(vec![create_ethanol()]);
+        assert_eq!(top.get_positions().unwrap(), None);
+    }
 }
@@ -7,8 +7,52 @@ def label_molecules(ff, top):
     for t in tmp:
         r = {}
         for k, v in t.items():
+            if k == "VirtualSites":
+                continue
             if isinstance(v, (ValenceDict, ImproperDict)):
                 v = v.store
             r[k] = v
         ret.append(r)
     return ret
+
+
+def label_virtual_sites(ff, top):
+    return [dict(t.get("VirtualSites", {})) for t in ff.label_molecules(top)]
+
+
+def charge_source(ff, molecule):
+    from openff.interchange.smirnoff._nonbonded import (
+        SMIRNOFFElectrostaticsCollection,
+    )
+
+    registered = ff.registered_parameter_handlers
+    # the order in which Interchange tries each charge handler
+    for tag in SMIRNOFFElectrostaticsCollection.parameter_handler_precedence():
+        if tag not in registered:
+            continue
+        if tag == "LibraryCharges":
+            # as in Interchange, library charges only apply if they cover the
+            # whole molecule
+            matches = ff[tag].find_matches(molecule.to_topology())
+            covered = {i for atoms in matches for i in atoms}
+            if len(covered) != molecule.n_atoms:
+                continue
+        return tag
+    return None
+
+
+def charge_method(ff, molecule):
+    source = charge_source(ff, molecule)
+    if source == "ToolkitAM1BCC":
+        return "am1bcc"
+    if source == "NAGLCharges":
+        return ff[source].model_file
+    return None
+
+
+def interchange_charge_sources(ff, molecule):
+    # the handlers Interchange itself used for the charges of molecule, to
+    # check charge_source against
+    interchange = ff.create_interchange(molecule.to_topology())
+    potentials = interchange.collections["Electrostatics"].potentials
+    return sorted({key.associated_handler for key in potentials})
@@ -1,26 +1,47 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::HashMap, fmt::Display, fs::File, io::Read, path::Path,
+    str::FromStr,
+};
 
 use openff_interchange::Interchange;
 use openff_units::Quantity;
 use pyo3::{
-    types::{IntoPyDict, PyDict, PyModule},
-    FromPyObject, Py, PyAny, PyResult, Python,
+    exceptions::{PyKeyError, PyValueError},
+    types::{IntoPyDict, PyBytes, PyDict, PyTuple},
+    FromPyObject, IntoPy, Py, PyAny, PyErr, PyResult, Python,
+};
+use utils::{
+    compile_attr, get_props, import, import_attr,
+    provenance::{Provenance, COSMETIC_PREFIX},
+    set_props,
 };
-use utils::{get_props, set_props};
 
-use crate::topology::{Molecule, Topology};
+use crate::{
+    topology::{Molecule, Topology},
+    utils::charge_cache::ChargeCache,
+    utils::constants::{ALLOWED_AROMATICITY_MODELS, DEFAULT_AROMATICITY_MODEL},
+};
 
 use super::{
     io::ParameterIOHandler,
-    parameters::{Parameter, ParameterHandler},
+    parameters::{
+        ChargeSource, Parameter, ParameterHandler, TypedParameterHandler,
+    },
 };
 
 const PYMODULE: &str = "openff.toolkit.typing.engines.smirnoff.forcefield";
 
+/// Return the function `name` from the Python helpers in `forcefield.py`.
+fn helper<'py>(py: Python<'py>, name: &'static str) -> &'py PyAny {
+    compile_attr(py, include_str!("forcefield.py"), "forcefield.py", name)
+        .unwrap()
+}
+
 pub fn get_available_force_fields() -> Vec<String> {
     Python::with_gil(|py| {
-        let m = PyModule::import(py, PYMODULE).unwrap();
-        m.call_method0("get_available_force_fields")
+        import_attr(py, PYMODULE, "get_available_force_fields")
+            .unwrap()
+            .call0()
             .unwrap()
             .extract()
             .unwrap()
@@ -43,16 +64,168 @@ impl Display for ForceField {
     }
 }
 
+/// Builder for a [ForceField] that exposes all of the keyword arguments to the
+/// Python constructor. Sources are loaded in the order they are added, so
+/// parameters from later sources take precedence over those from earlier ones.
+pub struct ForceFieldBuilder {
+    sources: Vec<String>,
+    aromaticity_model: String,
+    load_plugins: bool,
+    disable_version_check: bool,
+    allow_cosmetic_attributes: bool,
+}
+
+impl Default for ForceFieldBuilder {
+    fn default() -> Self {
+        Self {
+            sources: Vec::new(),
+            aromaticity_model: DEFAULT_AROMATICITY_MODEL.to_owned(),
+            load_plugins: false,
+            disable_version_check: false,
+            allow_cosmetic_attributes: true,
+        }
+    }
+}
+
+/// Decompress `bytes` if they start with a gzip or bzip2 header and decode the
+/// result as UTF-8.
+fn decode_source(bytes: &[u8]) -> PyResult<String> {
+    let module = match bytes {
+        [0x1f, 0x8b, ..] => Some("gzip"),
+        [b'B', b'Z', b'h', ..] => Some("bz2"),
+        _ => None,
+    };
+    let bytes = match module {
+        Some(module) => Python::with_gil(|py| -> PyResult<Vec<u8>> {
+            let m = import(py, module)?;
+            let out: &PyBytes = m
+                .call_method1("decompress", (PyBytes::new(py, bytes),))?
+                .downcast()?;
+            Ok(out.as_bytes().to_vec())
+        })?,
+        None => bytes.to_vec(),
+    };
+    String::from_utf8(bytes)
+        .map_err(|e| PyErr::new::<PyValueError, _>(e.to_string()))
+}
+
+impl ForceFieldBuilder {
+    /// Add a SMIRNOFF source, either the name of an installed force field, a
+    /// path to an offxml file, or the contents of one.
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.sources.push(source.into());
+        self
+    }
+
+    /// Add the contents of `reader` as a source. The contents may be plain
+    /// offxml or gzip- or bzip2-compressed offxml.
+    pub fn source_reader(mut self, mut reader: impl Read) -> PyResult<Self> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        self.sources.push(decode_source(&buf)?);
+        Ok(self)
+    }
+
+    /// Add each of `sources` in order. See [ForceFieldBuilder::source].
+    pub fn sources<S: Into<String>>(
+        mut self,
+        sources: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.sources.extend(sources.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set the aromaticity model. This must be one of
+    /// [ALLOWED_AROMATICITY_MODELS], or [ForceFieldBuilder::build] will fail.
+    pub fn aromaticity_model(mut self, model: impl Into<String>) -> Self {
+        self.aromaticity_model = model.into();
+        self
+    }
+
+    /// Load parameter handlers registered by installed plugins.
+    pub fn load_plugins(mut self, load_plugins: bool) -> Self {
+        self.load_plugins = load_plugins;
+        self
+    }
+
+    /// Skip checking the SMIRNOFF version of each source against the versions
+    /// supported by the toolkit.
+    pub fn disable_version_check(mut self, disable: bool) -> Self {
+        self.disable_version_check = disable;
+        self
+    }
+
+    /// Allow cosmetic attributes in the sources instead of raising an error.
+    /// Unlike in Python, this defaults to `true`.
+    pub fn allow_cosmetic_attributes(mut self, allow: bool) -> Self {
+        self.allow_cosmetic_attributes = allow;
+        self
+    }
+
+    /// Construct the [ForceField], returning an error for an unrecognized
+    /// aromaticity model or if any of the sources fail to load.
+    pub fn build(self) -> PyResult<ForceField> {
+        if !ALLOWED_AROMATICITY_MODELS
+            .contains(&self.aromaticity_model.as_str())
+        {
+            return Err(PyErr::new::<PyValueError, _>(format!(
+                "unknown aromaticity model `{}`, expected one of {:?}",
+                self.aromaticity_model, ALLOWED_AROMATICITY_MODELS
+            )));
+        }
+        Python::with_gil(|py| {
+            let ff = import_attr(py, PYMODULE, "ForceField")?;
+            let kwargs = [
+                ("load_plugins", self.load_plugins),
+                ("disable_version_check", self.disable_version_check),
+                ("allow_cosmetic_attributes", self.allow_cosmetic_attributes),
+            ]
+            .into_py_dict(py);
+            kwargs.set_item("aromaticity_model", self.aromaticity_model)?;
+            let sources = PyTuple::new(py, self.sources);
+            ff.call(sources, Some(kwargs))?.extract()
+        })
+    }
+}
+
+/// Load a ForceField from the contents of an offxml file.
+impl FromStr for ForceField {
+    type Err = PyErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::builder().source(s).build()
+    }
+}
+
 impl ForceField {
     /// Load a ForceField from one SMIRNOFF parameter definition file.
     pub fn load(path: &str) -> PyResult<Self> {
-        Python::with_gil(|py| {
-            let m = PyModule::import(py, PYMODULE)?;
-            let ff = m.getattr("ForceField")?;
-            // TODO handle kwargs, probably with a builder
-            let kwargs = [("allow_cosmetic_attributes", true)].into_py_dict(py);
-            ff.call((path,), Some(kwargs))?.extract()
-        })
+        Self::builder().source(path).build()
+    }
+
+    /// Load a ForceField from the contents of an offxml file, optionally
+    /// compressed with gzip or bzip2.
+    pub fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        Self::builder().source(decode_source(bytes)?).build()
+    }
+
+    /// Load a ForceField from the contents of `reader`. See
+    /// [ForceField::from_bytes].
+    pub fn from_reader(reader: impl Read) -> PyResult<Self> {
+        Self::builder().source_reader(reader)?.build()
+    }
+
+    /// Load a ForceField from the file at `path`. Unlike [ForceField::load],
+    /// this does not search the installed force field directories, but it does
+    /// handle `.offxml.gz` and `.offxml.bz2` files.
+    pub fn from_file(path: impl AsRef<Path>) -> PyResult<Self> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    /// Return a [ForceFieldBuilder] for loading a ForceField with non-default
+    /// options or from multiple sources.
+    pub fn builder() -> ForceFieldBuilder {
+        ForceFieldBuilder::default()
     }
 
     get_props! {
@@ -97,6 +270,30 @@ impl ForceField {
         })
     }
 
+    /// Like [ForceField::get_parameter_handler], but return the concrete
+    /// handler type `H`, whose tag name determines the handler to retrieve.
+    /// Unlike in Python, an error is returned if `self` does not have one, so
+    /// that looking up a handler never changes the force field. Use
+    /// [ForceField::get_or_add_handler] to register a new handler.
+    pub fn get_handler<H: TypedParameterHandler>(&self) -> PyResult<H> {
+        let registered = self.registered_parameter_handlers();
+        if !registered.iter().any(|tag| tag == H::TAGNAME) {
+            return Err(PyKeyError::new_err(format!(
+                "no {} handler is registered",
+                H::TAGNAME
+            )));
+        }
+        self.get_or_add_handler()
+    }
+
+    /// Like [ForceField::get_handler], but register a new, empty handler if
+    /// `self` does not already have one, as `get_parameter_handler` does in
+    /// Python.
+    pub fn get_or_add_handler<H: TypedParameterHandler>(&self) -> PyResult<H> {
+        self.get_parameter_handler(H::TAGNAME)
+            .and_then(|h| Python::with_gil(|py| h.0.extract(py)))
+    }
+
     pub fn get_parameter_io_handler(
         &self,
         io_format: &str,
@@ -118,10 +315,10 @@ impl ForceField {
         })
     }
 
-    /// Parse a SMIRNOFF force field definition. The Python docs claim that this
-    /// can be a "string or file-like object or open file handle or URL," but I
-    /// can't get it to work with a &str. It tries to iterate over the string
-    /// itself and read from files named by each character...
+    /// Parse a SMIRNOFF force field definition. `source` can be either the
+    /// name of a file or the contents of one. The Python method takes a list
+    /// of sources and iterates over it, so `source` is wrapped in a list here
+    /// to keep it from reading files named by each character of the string.
     pub fn parse_sources(&mut self, source: &str) {
         Python::with_gil(|py| {
             self.0
@@ -148,20 +345,112 @@ impl ForceField {
         })
     }
 
+    /// Record the software versions in `provenance` as cosmetic attributes on
+    /// the first registered parameter handler, replacing all of those recorded
+    /// before, so that files written by [ForceField::to_file] keep track of
+    /// what produced them. Returns an error if `self` has no parameter
+    /// handlers.
+    pub fn record_provenance(
+        &mut self,
+        provenance: &Provenance,
+    ) -> PyResult<()> {
+        let Some(tagname) =
+            self.registered_parameter_handlers().into_iter().next()
+        else {
+            return Err(PyValueError::new_err(
+                "cannot record provenance without a parameter handler",
+            ));
+        };
+        let mut handler = self.get_parameter_handler(&tagname)?;
+        for attr in handler.cosmetic_attributes().into_keys() {
+            if attr.starts_with(COSMETIC_PREFIX) {
+                handler.delete_cosmetic_attribute(&attr)?;
+            }
+        }
+        for (attr, val) in provenance.cosmetic_attributes() {
+            handler.add_cosmetic_attribute(&attr, &val);
+        }
+        Ok(())
+    }
+
+    /// Like [ForceField::to_file], but strip cosmetic attributes from the
+    /// force field and all of its parameters before writing.
+    pub fn to_file_discard_cosmetic(&self, filename: &str) {
+        Python::with_gil(|py| {
+            let kwargs =
+                [("discard_cosmetic_attributes", true)].into_py_dict(py);
+            self.0
+                .call_method(py, "to_file", (filename,), Some(kwargs))
+                .unwrap();
+        })
+    }
+
+    /// Like the [Display] implementation, but strip cosmetic attributes from
+    /// the output.
+    pub fn to_string_discard_cosmetic(&self) -> String {
+        Python::with_gil(|py| {
+            let kwargs =
+                [("discard_cosmetic_attributes", true)].into_py_dict(py);
+            self.0
+                .call_method(py, "to_string", (), Some(kwargs))
+                .unwrap()
+                .extract(py)
+                .unwrap()
+        })
+    }
+
+    /// Return copies of the unique molecules in `topology` whose charges come
+    /// from a [ForceField::charge_method], with their charges filled in from
+    /// [ChargeCache::global] under that method. Molecules whose charges cannot
+    /// be assigned are left out so that the toolkit reports the error itself.
+    fn cached_charges(&self, topology: &Topology) -> Vec<Molecule> {
+        topology
+            .unique_molecules()
+            .into_iter()
+            .filter_map(|mut mol| {
+                let method = self.charge_method(&mol)?;
+                let cache = ChargeCache::global();
+                ChargeCache::get_or_assign_shared(cache, &mut mol, &method)
+                    .ok()?
+                    .then_some(mol)
+            })
+            .collect()
+    }
+
+    /// Create an OpenMM System for `topology`. Charges computed by the toolkit
+    /// are taken from [ChargeCache::global] when possible.
     pub fn create_openmm_system(&self, topology: Topology) -> openmm::System {
+        let charged = self.cached_charges(&topology);
         Python::with_gil(|py| {
+            let kwargs = [("charge_from_molecules", charged.into_py(py))]
+                .into_py_dict(py);
             self.0
-                .call_method1(py, "create_openmm_system", (topology.0,))
+                .call_method(
+                    py,
+                    "create_openmm_system",
+                    (topology.0,),
+                    Some(kwargs),
+                )
                 .unwrap()
                 .extract(py)
                 .unwrap()
         })
     }
 
+    /// Create an Interchange for `topology`. Charges computed by the toolkit
+    /// are taken from [ChargeCache::global] when possible.
     pub fn create_interchange(&self, topology: Topology) -> Interchange {
+        let charged = self.cached_charges(&topology);
         Python::with_gil(|py| {
+            let kwargs = [("charge_from_molecules", charged.into_py(py))]
+                .into_py_dict(py);
             self.0
-                .call_method1(py, "create_interchange", (topology.0,))
+                .call_method(
+                    py,
+                    "create_interchange",
+                    (topology.0,),
+                    Some(kwargs),
+                )
                 .unwrap()
                 .extract(py)
                 .unwrap()
@@ -174,18 +463,65 @@ impl ForceField {
     /// are transformed into regular dicts. For ValenceDict, this loses the
     /// check on the first and last atom and the reversal to make the first less
     /// than the last. For ImproperDict, this loses the sorting of the atoms
-    /// other than the central atom at index 1
+    /// other than the central atom at index 1. Virtual site labels map to lists
+    /// of parameters rather than single parameters, so they are omitted here
+    /// and returned by [ForceField::label_virtual_sites] instead.
     pub fn label_molecules(
         &self,
         topology: Topology,
     ) -> Vec<HashMap<String, HashMap<Vec<usize>, Parameter>>> {
         Python::with_gil(|py| {
-            let fun =
-                PyModule::from_code(py, include_str!("forcefield.py"), "", "")
-                    .unwrap()
-                    .getattr("label_molecules")
-                    .unwrap();
-            fun.call1((&self.0, topology.0)).unwrap().extract().unwrap()
+            helper(py, "label_molecules")
+                .call1((&self.0, topology.0))
+                .unwrap()
+                .extract()
+                .unwrap()
+        })
+    }
+
+    /// Return the virtual site parameters assigned to each molecule in
+    /// `topology`, keyed by the atoms matched by their SMIRKS patterns. More
+    /// than one virtual site can be placed on the same set of atoms.
+    pub fn label_virtual_sites(
+        &self,
+        topology: Topology,
+    ) -> Vec<HashMap<Vec<usize>, Vec<Parameter>>> {
+        Python::with_gil(|py| {
+            helper(py, "label_virtual_sites")
+                .call1((&self.0, topology.0))
+                .unwrap()
+                .extract()
+                .unwrap()
+        })
+    }
+
+    /// Return the handler that assigns partial charges to `molecule`, or
+    /// `None` if `self` has no handler that can assign them. `LibraryCharges`
+    /// only apply when they cover every atom in the molecule, after which the
+    /// remaining handlers are tried in the order of [ChargeSource].
+    pub fn charge_source(&self, molecule: &Molecule) -> Option<ChargeSource> {
+        Python::with_gil(|py| {
+            helper(py, "charge_source")
+                .call1((&self.0, &molecule.0))
+                .unwrap()
+                .extract()
+                .unwrap()
+        })
+    }
+
+    /// Return the partial charge method that the handler from
+    /// [ForceField::charge_source] passes to the toolkit for `molecule`:
+    /// `am1bcc` for `ToolkitAM1BCC`, or the model file for `NAGLCharges`. This
+    /// is `None` for `LibraryCharges`, which are not computed, and for
+    /// `ChargeIncrementModel`, whose charges also depend on the increments in
+    /// the force field and so cannot be cached by method alone.
+    pub fn charge_method(&self, molecule: &Molecule) -> Option<String> {
+        Python::with_gil(|py| {
+            helper(py, "charge_method")
+                .call1((&self.0, &molecule.0))
+                .unwrap()
+                .extract()
+                .unwrap()
         })
     }
 
@@ -217,6 +553,29 @@ mod tests {
         ForceField::load("openff-2.1.0.offxml").unwrap();
     }
 
+    #[test]
+    fn builder() {
+        let ff = ForceField::builder()
+            .sources(["openff-2.1.0.offxml", "tip3p.offxml"])
+            .aromaticity_model(DEFAULT_AROMATICITY_MODEL)
+            .load_plugins(false)
+            .disable_version_check(true)
+            .allow_cosmetic_attributes(false)
+            .build()
+            .unwrap();
+        let lc = ff.get_parameter_handler("LibraryCharges").unwrap();
+        assert!(lc.get_parameter("q-tip3p-H1").is_some());
+    }
+
+    #[test]
+    fn builder_bad_aromaticity_model() {
+        let got = ForceField::builder()
+            .source("openff-2.1.0.offxml")
+            .aromaticity_model("MDL")
+            .build();
+        assert!(got.is_err());
+    }
+
     #[test]
     fn get_aromaticity_model() {
         let mdl = ForceField::load("openff-2.1.0.offxml")
@@ -257,6 +616,30 @@ mod tests {
         assert_eq!(got, ff.to_string());
     }
 
+    #[test]
+    fn record_provenance() {
+        let mut ff = ForceField::load("openff-2.1.0.offxml").unwrap();
+        let mut provenance = Provenance::gather();
+        provenance
+            .packages
+            .push(("stale".to_owned(), "1.0".to_owned()));
+        ff.record_provenance(&provenance).unwrap();
+        // recording again replaces all of the old attributes
+        provenance.packages.pop();
+        ff.record_provenance(&provenance).unwrap();
+        let got: ForceField = ff.to_string().parse().unwrap();
+        let first = &got.registered_parameter_handlers()[0];
+        let attrs = got
+            .get_parameter_handler(first)
+            .unwrap()
+            .cosmetic_attributes();
+        for (attr, val) in provenance.cosmetic_attributes() {
+            assert_eq!(attrs[&attr], val);
+        }
+        assert!(!attrs.contains_key("provenance_stale"));
+        assert!(!ff.to_string_discard_cosmetic().contains("provenance_"));
+    }
+
     #[test]
     fn parse_sources() {
         let mut ff = ForceField::load("openff-2.1.0.offxml").unwrap();
@@ -265,6 +648,118 @@ mod tests {
         ff.parse_sources(&s);
     }
 
+    #[test]
+    fn from_str() {
+        let s =
+            std::fs::read_to_string("../testfiles/sage-2.1.0.offxml").unwrap();
+        let ff: ForceField = s.parse().unwrap();
+        assert_eq!(
+            ff.to_string(),
+            ForceField::from_bytes(s.as_bytes()).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn from_compressed_file() {
+        let want = ForceField::from_file("../testfiles/sage-2.1.0.offxml")
+            .unwrap()
+            .to_string();
+        for ext in ["gz", "bz2"] {
+            let got = ForceField::from_file(format!(
+                "../testfiles/sage-2.1.0.offxml.{ext}"
+            ))
+            .unwrap();
+            assert_eq!(got.to_string(), want);
+        }
+    }
+
+    #[test]
+    fn virtual_sites() {
+        use crate::{
+            typing::engines::smirnoff::parameters::{
+                VirtualSiteHandler, VirtualSiteType,
+            },
+            Molecule,
+        };
+
+        let ff = ForceField::builder()
+            .sources(["openff-2.1.0.offxml", "tip4p_fb.offxml"])
+            .build()
+            .unwrap();
+        let vs: VirtualSiteHandler = ff.get_handler().unwrap();
+        let sites = vs.virtual_sites().unwrap();
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].site_type, VirtualSiteType::DivalentLonePair);
+
+        let water = || {
+            let mut water = Molecule::new();
+            water.add_atom(8, 0, false);
+            water.add_atom(1, 0, false);
+            water.add_atom(1, 0, false);
+            water.add_bond(0, 1, 1, false, 1.0);
+            water.add_bond(0, 2, 1, false, 1.0);
+            water
+        };
+
+        let labels = ff.label_virtual_sites(water().to_topology());
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].values().flatten().count(), 1);
+
+        let system = ff.create_openmm_system(water().to_topology());
+        assert_eq!(system.get_num_particles(), 4);
+        assert_eq!(system.virtual_sites(), vec![3]);
+    }
+
+    #[test]
+    fn charge_source() {
+        use crate::typing::engines::smirnoff::parameters::ChargeIncrementModelHandler;
+
+        let ff = ForceField::load("openff-2.1.0.offxml").unwrap();
+
+        let mut sodium = Molecule::new();
+        sodium.add_atom(11, 1, false);
+        assert_eq!(
+            ff.charge_source(&sodium),
+            Some(ChargeSource::LibraryCharges)
+        );
+        assert_eq!(ff.charge_method(&sodium), None);
+
+        let mut methane = Molecule::new();
+        methane.add_atom(6, 0, false);
+        for i in 1..=4 {
+            methane.add_atom(1, 0, false);
+            methane.add_bond(0, i, 1, false, 1.0);
+        }
+        assert_eq!(
+            ff.charge_source(&methane),
+            Some(ChargeSource::ToolkitAM1BCC)
+        );
+        assert_eq!(ff.charge_method(&methane).as_deref(), Some("am1bcc"));
+
+        // check against the handlers Interchange actually uses
+        for (mol, source) in
+            [(sodium, "LibraryCharges"), (methane, "ToolkitAM1BCC")]
+        {
+            let got: Vec<String> = Python::with_gil(|py| {
+                helper(py, "interchange_charge_sources")
+                    .call1((&ff.0, &mol.0))
+                    .unwrap()
+                    .extract()
+                    .unwrap()
+            });
+            assert!(
+                !got.is_empty() && got.iter().all(|h| h.starts_with(source)),
+                "{got:?} {source}"
+            );
+        }
+
+        // looking up a missing handler does not register it
+        assert!(ff.get_handler::<ChargeIncrementModelHandler>().is_err());
+        assert!(!ff
+            .registered_parameter_handlers()
+            .contains(&"ChargeIncrementModel".to_owned()));
+    }
+
     #[test]
     fn misc() {
         let ff = ForceField::load("openff-2.1.0.offxml").unwrap();
@@ -1,7 +1,9 @@
 //! In many cases here, only the base classes are implemented because the inner
 //! PyAny will call the correct implementation on its own. Eventually it might
 //! be useful to define the base classes as traits and the concrete classes as
-//! structs, but this is a simpler start
+//! structs, but this is a simpler start. The concrete parameter handlers are
+//! available as structs implementing [parameters::TypedParameterHandler] for
+//! access to their handler-level attributes.
 
 pub mod forcefield;
 pub mod parameters;
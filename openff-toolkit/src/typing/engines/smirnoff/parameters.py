@@ -1,6 +1,152 @@
+from openff.toolkit.typing.engines.smirnoff.parameters import (
+    BondHandler,
+    ProperTorsionHandler,
+    VirtualSiteHandler,
+)
+from openff.units import unit
+
+K_BOND_UNIT = unit.kilocalorie / unit.mole / unit.angstrom**2
+K_TORSION_UNIT = unit.kilocalorie / unit.mole
+
+
 def get_k(param):
     if not isinstance(param.k, list):
         k = [param.k]
     else:
         k = param.k
     return [v.magnitude for v in k]
+
+
+def get_charges(param, attr):
+    return [q.m_as(unit.elementary_charge) for q in getattr(param, attr)]
+
+
+def set_charges(param, attr, charges):
+    setattr(param, attr, [q * unit.elementary_charge for q in charges])
+
+
+def get_cosmetic_attributes(obj):
+    return {
+        attr: str(getattr(obj, "_" + attr)) for attr in obj._cosmetic_attribs
+    }
+
+
+def _k_unit(param):
+    if isinstance(param, ProperTorsionHandler.ProperTorsionType):
+        return K_TORSION_UNIT
+    return K_BOND_UNIT
+
+
+def _magnitudes(bondorders, u):
+    return {bo: v.m_as(u) for bo, v in bondorders.items()}
+
+
+def _quantities(bondorders, u):
+    return {bo: v * u for bo, v in bondorders.items()}
+
+
+def get_k_bondorder(param):
+    kb = getattr(param, "k_bondorder", None)
+    if kb is None:
+        return None
+    if not isinstance(kb, list):
+        kb = [kb]
+    return [_magnitudes(k, _k_unit(param)) for k in kb]
+
+
+def set_k_bondorder(param, k_bondorder):
+    u = _k_unit(param)
+    kb = [_quantities(k, u) for k in k_bondorder]
+    if isinstance(param, ProperTorsionHandler.ProperTorsionType):
+        param.k_bondorder = kb
+    else:
+        (param.k_bondorder,) = kb
+
+
+def get_length_bondorder(param):
+    lb = getattr(param, "length_bondorder", None)
+    if lb is None:
+        return None
+    return _magnitudes(lb, unit.angstrom)
+
+
+def set_length_bondorder(param, length_bondorder):
+    param.length_bondorder = _quantities(length_bondorder, unit.angstrom)
+
+
+def interpolated_bond(smirks, id, k_bondorder, length_bondorder):
+    kwargs = dict(smirks=smirks, id=id)
+    for bo, k in k_bondorder.items():
+        kwargs[f"k_bondorder{bo}"] = k * K_BOND_UNIT
+    for bo, length in length_bondorder.items():
+        kwargs[f"length_bondorder{bo}"] = length * unit.angstrom
+    return BondHandler.BondType(**kwargs)
+
+
+def interpolated_torsion(smirks, id, periodicity, phase, idivf, k_bondorder):
+    kwargs = dict(smirks=smirks, id=id)
+    terms = zip(periodicity, phase, idivf, k_bondorder)
+    for i, (n, ph, d, kb) in enumerate(terms, start=1):
+        kwargs[f"periodicity{i}"] = n
+        kwargs[f"phase{i}"] = ph * unit.degree
+        kwargs[f"idivf{i}"] = d
+        for bo, k in kb.items():
+            kwargs[f"k{i}_bondorder{bo}"] = k * K_TORSION_UNIT
+    return ProperTorsionHandler.ProperTorsionType(**kwargs)
+
+
+def get_virtual_site(param):
+    def m_as(q, u):
+        return None if q is None else q.m_as(u)
+
+    rmin_half = param.rmin_half
+    if rmin_half is None:
+        rmin_half = param.sigma * 2 ** (1 / 6) / 2
+    return dict(
+        smirks=param.smirks,
+        id=param.id,
+        name=param.name,
+        type=param.type,
+        match=getattr(param, "match"),
+        distance=param.distance.m_as(unit.angstrom),
+        outOfPlaneAngle=m_as(param.outOfPlaneAngle, unit.degree),
+        inPlaneAngle=m_as(param.inPlaneAngle, unit.degree),
+        charge_increment=[
+            q.m_as(unit.elementary_charge) for q in param.charge_increment
+        ],
+        epsilon=param.epsilon.m_as(unit.kilocalorie / unit.mole),
+        rmin_half=rmin_half.m_as(unit.angstrom),
+    )
+
+
+def virtual_site(
+    smirks,
+    id,
+    name,
+    type,
+    match,
+    distance,
+    out_of_plane_angle,
+    in_plane_angle,
+    charge_increment,
+    epsilon,
+    rmin_half,
+):
+    kwargs = dict(
+        smirks=smirks,
+        name=name,
+        type=type,
+        match=match,
+        distance=distance * unit.angstrom,
+        epsilon=epsilon * unit.kilocalorie / unit.mole,
+        rmin_half=rmin_half * unit.angstrom,
+    )
+    if id is not None:
+        kwargs["id"] = id
+    if out_of_plane_angle is not None:
+        kwargs["outOfPlaneAngle"] = out_of_plane_angle * unit.degree
+    if in_plane_angle is not None:
+        kwargs["inPlaneAngle"] = in_plane_angle * unit.degree
+    for i, q in enumerate(charge_increment, start=1):
+        kwargs[f"charge_increment{i}"] = q * unit.elementary_charge
+    return VirtualSiteHandler.VirtualSiteType(**kwargs)
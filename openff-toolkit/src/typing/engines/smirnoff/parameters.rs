@@ -1,11 +1,85 @@
-//! I've never constructed something in here directly, so I'm just skipping any
-//! other features for now
+//! Most of the types here are only retrieved from an existing
+//! [ForceField](crate::ForceField). The exceptions are the bond order-
+//! interpolated parameters, which can be constructed directly with
+//! [Parameter::interpolated_bond] and [Parameter::interpolated_torsion].
 
+use std::collections::{BTreeMap, HashMap};
+
+use openff_units::Quantity;
 use pyo3::{
-    types::{IntoPyDict, PyList, PyModule},
-    FromPyObject, Py, PyAny, PyObject, Python,
+    exceptions::PyTypeError,
+    types::{IntoPyDict, PyList},
+    FromPyObject, IntoPy, Py, PyAny, PyErr, PyObject, PyResult, Python,
 };
-use utils::{get_props, set_props};
+use utils::{compile_attr, get_props, set_props};
+
+/// Return the function `name` from the Python helpers in `parameters.py`.
+fn helper<'py>(py: Python<'py>, name: &'static str) -> &'py PyAny {
+    compile_attr(py, include_str!("parameters.py"), "parameters.py", name)
+        .unwrap()
+}
+
+/// Generate methods for reading and writing the cosmetic attributes shared by
+/// [ParameterHandler] and [Parameter] through their common Python base class.
+macro_rules! cosmetic_attributes {
+    ($($struct:ident$(,)?)*) => {
+        $(impl $struct {
+            /// Return a map of cosmetic attribute names to their values.
+            pub fn cosmetic_attributes(&self) -> HashMap<String, String> {
+                Python::with_gil(|py| {
+                    helper(py, "get_cosmetic_attributes")
+                        .call1((&self.0,))
+                        .unwrap()
+                        .extract()
+                        .unwrap()
+                })
+            }
+
+            /// Add a cosmetic attribute named `attr` with value `val`. These
+            /// are written back out by [ForceField::to_file] unless they are
+            /// explicitly discarded.
+            ///
+            /// [ForceField::to_file]: crate::ForceField::to_file
+            pub fn add_cosmetic_attribute(&mut self, attr: &str, val: &str) {
+                Python::with_gil(|py| {
+                    self.0
+                        .call_method1(
+                            py,
+                            "add_cosmetic_attribute",
+                            (attr, val),
+                        )
+                        .unwrap();
+                })
+            }
+
+            /// Delete the cosmetic attribute named `attr`, returning an error
+            /// if there is no such attribute.
+            pub fn delete_cosmetic_attribute(
+                &mut self,
+                attr: &str,
+            ) -> PyResult<()> {
+                Python::with_gil(|py| {
+                    self.0.call_method1(
+                        py,
+                        "delete_cosmetic_attribute",
+                        (attr,),
+                    )?;
+                    Ok(())
+                })
+            }
+
+            pub fn attribute_is_cosmetic(&self, attr: &str) -> bool {
+                Python::with_gil(|py| {
+                    self.0
+                        .call_method1(py, "attribute_is_cosmetic", (attr,))
+                        .unwrap()
+                        .extract(py)
+                        .unwrap()
+                })
+            }
+        })*
+    }
+}
 
 #[derive(FromPyObject)]
 pub struct ParameterHandler(pub(crate) Py<PyAny>);
@@ -15,6 +89,32 @@ impl ParameterHandler {
         parameters, Vec<Parameter>;
     }
 
+    /// Return the SMIRNOFF tag name handled by `self`, such as `Bonds`.
+    pub fn tagname(&self) -> String {
+        Python::with_gil(|py| {
+            self.0.getattr(py, "_TAGNAME").unwrap().extract(py).unwrap()
+        })
+    }
+
+    /// Return the version of the SMIRNOFF section handled by `self`.
+    pub fn version(&self) -> String {
+        Python::with_gil(|py| {
+            self.0
+                .getattr(py, "version")
+                .unwrap()
+                .as_ref(py)
+                .str()
+                .unwrap()
+                .to_string()
+        })
+    }
+
+    pub fn set_version(&mut self, version: &str) {
+        Python::with_gil(|py| {
+            self.0.setattr(py, "version", version).unwrap();
+        })
+    }
+
     pub fn get_parameter(&self, id: &str) -> Option<Parameter> {
         Python::with_gil(|py| {
             let obj: PyObject = self
@@ -54,6 +154,423 @@ impl ParameterHandler {
     }
 }
 
+/// A concrete [ParameterHandler] subclass, identified by the tag name it parses
+/// from SMIRNOFF files.
+pub trait TypedParameterHandler:
+    for<'a> FromPyObject<'a> + Into<ParameterHandler>
+{
+    const TAGNAME: &'static str;
+}
+
+/// Generate the [ParameterHandler] subclasses named by the keys, each of which
+/// handles the SMIRNOFF tag given by its value. These can be converted to and
+/// from a [ParameterHandler] to access the methods on the base class.
+macro_rules! parameter_handlers {
+    ($($name:ident => $tagname:literal$(,)?)*) => {
+        $(#[derive(FromPyObject)]
+        pub struct $name(pub(crate) Py<PyAny>);
+
+        impl TypedParameterHandler for $name {
+            const TAGNAME: &'static str = $tagname;
+        }
+
+        impl From<$name> for ParameterHandler {
+            fn from(value: $name) -> Self {
+                Self(value.0)
+            }
+        }
+
+        impl TryFrom<ParameterHandler> for $name {
+            type Error = PyErr;
+
+            fn try_from(value: ParameterHandler) -> Result<Self, Self::Error> {
+                let tagname = value.tagname();
+                if tagname != $tagname {
+                    return Err(PyErr::new::<PyTypeError, _>(format!(
+                        "expected a {} handler, got {tagname}",
+                        $tagname
+                    )));
+                }
+                Ok(Self(value.0))
+            }
+        }
+
+        impl $name {
+            /// Return a new reference to `self` as a [ParameterHandler].
+            pub fn handler(&self) -> ParameterHandler {
+                Python::with_gil(|py| ParameterHandler(self.0.clone_ref(py)))
+            }
+
+            pub fn version(&self) -> String {
+                self.handler().version()
+            }
+
+            pub fn set_version(&mut self, version: &str) {
+                self.handler().set_version(version)
+            }
+        })*
+    }
+}
+
+parameter_handlers! {
+    ConstraintHandler => "Constraints",
+    BondHandler => "Bonds",
+    AngleHandler => "Angles",
+    ProperTorsionHandler => "ProperTorsions",
+    ImproperTorsionHandler => "ImproperTorsions",
+    VdWHandler => "vdW",
+    ElectrostaticsHandler => "Electrostatics",
+    VirtualSiteHandler => "VirtualSites",
+    LibraryChargeHandler => "LibraryCharges",
+    ChargeIncrementModelHandler => "ChargeIncrementModel",
+    ToolkitAM1BCCHandler => "ToolkitAM1BCC",
+    NAGLChargesHandler => "NAGLCharges",
+}
+
+/// The handlers that can assign partial charges to a molecule, in the order of
+/// precedence used when more than one of them is present in a force field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChargeSource {
+    LibraryCharges,
+    NAGLCharges,
+    ChargeIncrementModel,
+    ToolkitAM1BCC,
+}
+
+impl<'source> FromPyObject<'source> for ChargeSource {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract()? {
+            LibraryChargeHandler::TAGNAME => Ok(Self::LibraryCharges),
+            NAGLChargesHandler::TAGNAME => Ok(Self::NAGLCharges),
+            ChargeIncrementModelHandler::TAGNAME => {
+                Ok(Self::ChargeIncrementModel)
+            }
+            ToolkitAM1BCCHandler::TAGNAME => Ok(Self::ToolkitAM1BCC),
+            s => Err(PyErr::new::<PyTypeError, _>(format!(
+                "unrecognized charge handler `{s}`"
+            ))),
+        }
+    }
+}
+
+impl BondHandler {
+    get_props! {
+        potential, String;
+        fractional_bondorder_method, String;
+        fractional_bondorder_interpolation, String;
+    }
+
+    set_props! {
+        set_potential => potential;
+        set_fractional_bondorder_method => fractional_bondorder_method;
+        set_fractional_bondorder_interpolation => fractional_bondorder_interpolation;
+    }
+}
+
+impl AngleHandler {
+    get_props! {
+        potential, String;
+    }
+
+    set_props! {
+        set_potential => potential;
+    }
+}
+
+impl ProperTorsionHandler {
+    get_props! {
+        potential, String;
+        fractional_bondorder_method, String;
+        fractional_bondorder_interpolation, String;
+    }
+
+    set_props! {
+        set_potential => potential;
+        set_fractional_bondorder_method => fractional_bondorder_method;
+        set_fractional_bondorder_interpolation => fractional_bondorder_interpolation;
+    }
+}
+
+impl ImproperTorsionHandler {
+    get_props! {
+        potential, String;
+    }
+
+    set_props! {
+        set_potential => potential;
+    }
+}
+
+impl VdWHandler {
+    get_props! {
+        potential, String;
+        combining_rules, String;
+        scale12, f64;
+        scale13, f64;
+        scale14, f64;
+        scale15, f64;
+        cutoff, Quantity;
+        switch_width, Quantity;
+        periodic_method, String;
+        nonperiodic_method, String;
+    }
+
+    set_props! {
+        set_potential => potential;
+        set_combining_rules => combining_rules;
+        set_scale12 => scale12;
+        set_scale13 => scale13;
+        set_scale14 => scale14;
+        set_scale15 => scale15;
+        set_cutoff => cutoff;
+        set_switch_width => switch_width;
+        set_periodic_method => periodic_method;
+        set_nonperiodic_method => nonperiodic_method;
+    }
+}
+
+impl ElectrostaticsHandler {
+    get_props! {
+        scale12, f64;
+        scale13, f64;
+        scale14, f64;
+        scale15, f64;
+        cutoff, Quantity;
+        switch_width, Quantity;
+        periodic_potential, String;
+        nonperiodic_potential, String;
+        exception_potential, String;
+    }
+
+    set_props! {
+        set_scale12 => scale12;
+        set_scale13 => scale13;
+        set_scale14 => scale14;
+        set_scale15 => scale15;
+        set_cutoff => cutoff;
+        set_switch_width => switch_width;
+        set_periodic_potential => periodic_potential;
+        set_nonperiodic_potential => nonperiodic_potential;
+        set_exception_potential => exception_potential;
+    }
+}
+
+/// The values of a parameter at integral bond orders, from which the value at a
+/// fractional bond order is found by linear interpolation. These correspond to
+/// attributes like `k_bondorder1` and `k_bondorder2` in SMIRNOFF files.
+#[derive(Clone, Debug, Default, PartialEq, FromPyObject)]
+#[pyo3(transparent)]
+pub struct BondOrderMap(pub BTreeMap<usize, f64>);
+
+impl BondOrderMap {
+    /// Evaluate the parameter at `fractional_bond_order` by linear
+    /// interpolation between the neighboring bond orders, or by linear
+    /// extrapolation from the two closest bond orders if it falls outside of
+    /// the range of bond orders in `self`. This matches the `linear`
+    /// `fractional_bondorder_interpolation` in the toolkit. Returns `None` if
+    /// `fractional_bond_order` is not itself in `self` and there are fewer
+    /// than two points to interpolate between.
+    pub fn evaluate(&self, fractional_bond_order: f64) -> Option<f64> {
+        let points: Vec<(f64, f64)> =
+            self.0.iter().map(|(&bo, &v)| (bo as f64, v)).collect();
+        if let Some(&(_, v)) =
+            points.iter().find(|(bo, _)| *bo == fractional_bond_order)
+        {
+            return Some(v);
+        }
+        if points.len() < 2 {
+            return None;
+        }
+        let above = points
+            .iter()
+            .position(|(bo, _)| *bo > fractional_bond_order)
+            .unwrap_or(points.len() - 1)
+            .max(1);
+        let (x0, y0) = points[above - 1];
+        let (x1, y1) = points[above];
+        Some(y0 + (y1 - y0) / (x1 - x0) * (fractional_bond_order - x0))
+    }
+}
+
+impl IntoPy<Py<PyAny>> for BondOrderMap {
+    fn into_py(self, py: Python<'_>) -> Py<PyAny> {
+        self.0.into_py(py)
+    }
+}
+
+impl ChargeIncrementModelHandler {
+    get_props! {
+        number_of_conformers, usize;
+        partial_charge_method, String;
+    }
+
+    set_props! {
+        set_number_of_conformers => number_of_conformers;
+        set_partial_charge_method => partial_charge_method;
+    }
+}
+
+impl NAGLChargesHandler {
+    get_props! {
+        model_file, String;
+    }
+
+    set_props! {
+        set_model_file => model_file;
+    }
+}
+
+impl VirtualSiteHandler {
+    get_props! {
+        exclusion_policy, String;
+    }
+
+    set_props! {
+        set_exclusion_policy => exclusion_policy;
+    }
+
+    /// Return all of the parameters in `self` as [VirtualSite]s, or an error
+    /// if any of them has a `type` or `match` that [VirtualSite] does not
+    /// cover.
+    pub fn virtual_sites(&self) -> PyResult<Vec<VirtualSite>> {
+        self.handler()
+            .parameters()
+            .iter()
+            .map(VirtualSite::try_from)
+            .collect()
+    }
+
+    pub fn add_virtual_site(&mut self, site: &VirtualSite) -> PyResult<()> {
+        self.handler().add_parameter(site.to_parameter()?);
+        Ok(())
+    }
+}
+
+/// The geometry used to place a virtual site relative to its parent atoms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VirtualSiteType {
+    BondCharge,
+    MonovalentLonePair,
+    DivalentLonePair,
+    TrivalentLonePair,
+}
+
+impl VirtualSiteType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VirtualSiteType::BondCharge => "BondCharge",
+            VirtualSiteType::MonovalentLonePair => "MonovalentLonePair",
+            VirtualSiteType::DivalentLonePair => "DivalentLonePair",
+            VirtualSiteType::TrivalentLonePair => "TrivalentLonePair",
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for VirtualSiteType {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract()? {
+            "BondCharge" => Ok(Self::BondCharge),
+            "MonovalentLonePair" => Ok(Self::MonovalentLonePair),
+            "DivalentLonePair" => Ok(Self::DivalentLonePair),
+            "TrivalentLonePair" => Ok(Self::TrivalentLonePair),
+            s => Err(PyErr::new::<PyTypeError, _>(format!(
+                "unrecognized virtual site type `{s}`"
+            ))),
+        }
+    }
+}
+
+/// Whether a virtual site is applied once per match of its SMIRKS pattern or
+/// once per permutation of the matched atoms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VirtualSiteMatch {
+    AllPermutations,
+    Once,
+}
+
+impl VirtualSiteMatch {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VirtualSiteMatch::AllPermutations => "all_permutations",
+            VirtualSiteMatch::Once => "once",
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for VirtualSiteMatch {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract()? {
+            "all_permutations" => Ok(Self::AllPermutations),
+            "once" => Ok(Self::Once),
+            s => Err(PyErr::new::<PyTypeError, _>(format!(
+                "unrecognized virtual site match `{s}`"
+            ))),
+        }
+    }
+}
+
+/// A native representation of a parameter from the `VirtualSites` handler.
+/// Distances and `rmin_half` are in Å, angles are in degrees, charge increments
+/// are in elementary charges, and `epsilon` is in kcal/mol.
+#[derive(Clone, Debug, PartialEq, FromPyObject)]
+pub struct VirtualSite {
+    #[pyo3(item)]
+    pub smirks: String,
+    #[pyo3(item)]
+    pub id: Option<String>,
+    #[pyo3(item)]
+    pub name: String,
+    #[pyo3(item("type"))]
+    pub site_type: VirtualSiteType,
+    #[pyo3(item("match"))]
+    pub match_mode: VirtualSiteMatch,
+    #[pyo3(item)]
+    pub distance: f64,
+    #[pyo3(item("outOfPlaneAngle"))]
+    pub out_of_plane_angle: Option<f64>,
+    #[pyo3(item("inPlaneAngle"))]
+    pub in_plane_angle: Option<f64>,
+    #[pyo3(item)]
+    pub charge_increment: Vec<f64>,
+    #[pyo3(item)]
+    pub epsilon: f64,
+    #[pyo3(item)]
+    pub rmin_half: f64,
+}
+
+impl VirtualSite {
+    /// Construct a new virtual site [Parameter] from `self`.
+    pub fn to_parameter(&self) -> PyResult<Parameter> {
+        Python::with_gil(|py| {
+            helper(py, "virtual_site")
+                .call1((
+                    self.smirks.as_str(),
+                    self.id.as_deref(),
+                    self.name.as_str(),
+                    self.site_type.as_str(),
+                    self.match_mode.as_str(),
+                    self.distance,
+                    self.out_of_plane_angle,
+                    self.in_plane_angle,
+                    self.charge_increment.clone(),
+                    self.epsilon,
+                    self.rmin_half,
+                ))?
+                .extract()
+        })
+    }
+}
+
+impl TryFrom<&Parameter> for VirtualSite {
+    type Error = PyErr;
+
+    fn try_from(value: &Parameter) -> Result<Self, Self::Error> {
+        Python::with_gil(|py| {
+            helper(py, "get_virtual_site").call1((&value.0,))?.extract()
+        })
+    }
+}
+
 #[derive(FromPyObject)]
 pub struct Parameter(pub(crate) Py<PyAny>);
 
@@ -72,12 +589,128 @@ impl Parameter {
     /// Return all of the parameter's force constants as a vector.
     pub fn k(&self) -> Vec<f64> {
         Python::with_gil(|py| {
-            let fun =
-                PyModule::from_code(py, include_str!("parameters.py"), "", "")
-                    .unwrap()
-                    .getattr("get_k")
-                    .unwrap();
-            fun.call1((&self.0,)).unwrap().extract().unwrap()
+            helper(py, "get_k")
+                .call1((&self.0,))
+                .unwrap()
+                .extract()
+                .unwrap()
+        })
+    }
+
+    /// Return the per-atom charges of a `LibraryCharges` parameter in
+    /// elementary charges.
+    pub fn charge(&self) -> Vec<f64> {
+        self.charges("charge")
+    }
+
+    pub fn set_charge(&mut self, charge: Vec<f64>) {
+        self.set_charges("charge", charge)
+    }
+
+    /// Return the per-atom charge increments of a `ChargeIncrementModel` or
+    /// `VirtualSites` parameter in elementary charges.
+    pub fn charge_increment(&self) -> Vec<f64> {
+        self.charges("charge_increment")
+    }
+
+    pub fn set_charge_increment(&mut self, charge_increment: Vec<f64>) {
+        self.set_charges("charge_increment", charge_increment)
+    }
+
+    fn charges(&self, attr: &str) -> Vec<f64> {
+        Python::with_gil(|py| {
+            helper(py, "get_charges")
+                .call1((&self.0, attr))
+                .unwrap()
+                .extract()
+                .unwrap()
+        })
+    }
+
+    fn set_charges(&mut self, attr: &str, charges: Vec<f64>) {
+        Python::with_gil(|py| {
+            helper(py, "set_charges")
+                .call1((&self.0, attr, charges))
+                .unwrap();
+        })
+    }
+
+    /// Construct a new bond parameter whose force constant and length are both
+    /// interpolated by fractional bond order. Force constants are in
+    /// kcal/mol/Å² and lengths are in Å.
+    pub fn interpolated_bond(
+        smirks: &str,
+        id: &str,
+        k_bondorder: BondOrderMap,
+        length_bondorder: BondOrderMap,
+    ) -> PyResult<Self> {
+        Python::with_gil(|py| {
+            helper(py, "interpolated_bond")
+                .call1((smirks, id, k_bondorder, length_bondorder))?
+                .extract()
+        })
+    }
+
+    /// Construct a new proper torsion parameter whose force constants are
+    /// interpolated by fractional bond order. There is one term for each entry
+    /// in the arguments, which must all have the same length. Force constants
+    /// are in kcal/mol and phases are in degrees.
+    pub fn interpolated_torsion(
+        smirks: &str,
+        id: &str,
+        periodicity: Vec<usize>,
+        phase: Vec<f64>,
+        idivf: Vec<f64>,
+        k_bondorder: Vec<BondOrderMap>,
+    ) -> PyResult<Self> {
+        Python::with_gil(|py| {
+            helper(py, "interpolated_torsion")
+                .call1((smirks, id, periodicity, phase, idivf, k_bondorder))?
+                .extract()
+        })
+    }
+
+    /// Return the bond order-interpolated force constants, if `self` has them.
+    /// As in [Parameter::k], this is always a vector, with a single element
+    /// for bonds and one element per term for torsions. Bond force constants
+    /// are in kcal/mol/Å², and torsion force constants are in kcal/mol.
+    pub fn k_bondorder(&self) -> Option<Vec<BondOrderMap>> {
+        Python::with_gil(|py| {
+            helper(py, "get_k_bondorder")
+                .call1((&self.0,))
+                .unwrap()
+                .extract()
+                .unwrap()
+        })
+    }
+
+    /// Set the bond order-interpolated force constants on `self`. This should
+    /// contain a single element for bonds. See [Parameter::k_bondorder].
+    pub fn set_k_bondorder(&mut self, k_bondorder: Vec<BondOrderMap>) {
+        Python::with_gil(|py| {
+            helper(py, "set_k_bondorder")
+                .call1((&self.0, k_bondorder))
+                .unwrap();
+        })
+    }
+
+    /// Return the bond order-interpolated length in Å, if `self` has one.
+    pub fn length_bondorder(&self) -> Option<BondOrderMap> {
+        Python::with_gil(|py| {
+            helper(py, "get_length_bondorder")
+                .call1((&self.0,))
+                .unwrap()
+                .extract()
+                .unwrap()
+        })
+    }
+
+    /// Set the bond order-interpolated length in Å on `self`.
+    pub fn set_length_bondorder(&mut self, length_bondorder: BondOrderMap) {
+        Python::with_gil(|py| {
+            helper(py, "set_length_bondorder")
+                .call1((&self.0, length_bondorder))
+                .unwrap();
         })
     }
 
@@ -86,8 +719,14 @@ impl Parameter {
     }
 }
 
+cosmetic_attributes! {
+    ParameterHandler,
+    Parameter,
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::ForceField;
 
     #[test]
@@ -104,4 +743,176 @@ mod tests {
         assert_eq!(ps[0].k().len(), 1);
         assert_eq!(ps[8].k().len(), 1);
     }
+
+    #[test]
+    fn evaluate_bond_order_map() {
+        let m = BondOrderMap(BTreeMap::from([(1, 100.0), (2, 200.0)]));
+        assert_eq!(m.evaluate(1.0), Some(100.0));
+        assert_eq!(m.evaluate(2.0), Some(200.0));
+        assert_eq!(m.evaluate(1.25), Some(125.0));
+        assert_eq!(m.evaluate(0.5), Some(50.0));
+        assert_eq!(m.evaluate(2.5), Some(250.0));
+
+        let m = BondOrderMap(BTreeMap::from([(1, 1.0), (2, 2.0), (3, 6.0)]));
+        assert_eq!(m.evaluate(2.5), Some(4.0));
+        assert_eq!(m.evaluate(3.5), Some(8.0));
+        assert_eq!(m.evaluate(0.5), Some(0.5));
+
+        let m = BondOrderMap(BTreeMap::from([(1, 1.0)]));
+        assert_eq!(m.evaluate(1.0), Some(1.0));
+        assert_eq!(m.evaluate(1.5), None);
+    }
+
+    #[test]
+    fn interpolated_parameters() {
+        let ff = ForceField::load("openff-2.1.0.offxml").unwrap();
+
+        let k = BondOrderMap(BTreeMap::from([(1, 300.0), (2, 700.0)]));
+        let length = BondOrderMap(BTreeMap::from([(1, 1.5), (2, 1.3)]));
+        let bond = Parameter::interpolated_bond(
+            "[#6X3:1]-[#6X3:2]",
+            "b-interp",
+            k.clone(),
+            length.clone(),
+        )
+        .unwrap();
+        let mut h = ff.get_parameter_handler("Bonds").unwrap();
+        h.add_parameter(bond);
+        let mut bond = h.get_parameter("b-interp").unwrap();
+        assert_eq!(bond.k_bondorder(), Some(vec![k]));
+        assert_eq!(bond.length_bondorder(), Some(length));
+        let k = BondOrderMap(BTreeMap::from([(1, 250.0), (2, 650.0)]));
+        bond.set_k_bondorder(vec![k.clone()]);
+        assert_eq!(bond.k_bondorder(), Some(vec![k]));
+
+        let ks = vec![
+            BondOrderMap(BTreeMap::from([(1, 1.0), (2, 1.5)])),
+            BondOrderMap(BTreeMap::from([(1, 0.1), (2, 0.2)])),
+        ];
+        let torsion = Parameter::interpolated_torsion(
+            "[*:1]~[#6X3:2]-[#6X3:3]~[*:4]",
+            "t-interp",
+            vec![1, 2],
+            vec![0.0, 180.0],
+            vec![1.0, 1.0],
+            ks.clone(),
+        )
+        .unwrap();
+        assert_eq!(torsion.k_bondorder(), Some(ks));
+        assert_eq!(torsion.length_bondorder(), None);
+
+        let h = ff.get_parameter_handler("Bonds").unwrap();
+        assert_eq!(h.parameters()[0].k_bondorder(), None);
+    }
+
+    #[test]
+    fn virtual_site_round_trip() {
+        let ff = ForceField::load("openff-2.1.0.offxml").unwrap();
+        // Sage doesn't have virtual sites, so this creates an empty handler
+        assert!(ff.get_handler::<VirtualSiteHandler>().is_err());
+        let mut h: VirtualSiteHandler = ff.get_or_add_handler().unwrap();
+        let site = VirtualSite {
+            smirks: "[#6:2]-[#17X1:1]".to_owned(),
+            id: Some("v-Cl".to_owned()),
+            name: "EP".to_owned(),
+            site_type: VirtualSiteType::BondCharge,
+            match_mode: VirtualSiteMatch::AllPermutations,
+            distance: 1.45,
+            out_of_plane_angle: None,
+            in_plane_angle: None,
+            charge_increment: vec![0.1, 0.0],
+            epsilon: 0.0,
+            rmin_half: 0.0,
+        };
+        h.add_virtual_site(&site).unwrap();
+        let vs: VirtualSiteHandler = ff.get_handler().unwrap();
+        assert_eq!(vs.virtual_sites().unwrap(), vec![site]);
+    }
+
+    #[test]
+    fn charge_handlers() {
+        let ff = ForceField::load("openff-2.1.0.offxml").unwrap();
+        let lc: LibraryChargeHandler = ff.get_handler().unwrap();
+        let mut na = lc.handler().get_parameter("Na+").unwrap();
+        assert_eq!(na.charge(), vec![1.0]);
+        na.set_charge(vec![0.9]);
+        assert_eq!(na.charge(), vec![0.9]);
+
+        let _: ToolkitAM1BCCHandler = ff.get_handler().unwrap();
+
+        let mut cim: ChargeIncrementModelHandler =
+            ff.get_or_add_handler().unwrap();
+        cim.set_partial_charge_method("gasteiger");
+        assert_eq!(cim.partial_charge_method(), "gasteiger");
+    }
+
+    #[test]
+    fn typed_handlers() {
+        let ff = ForceField::load("openff-2.1.0.offxml").unwrap();
+
+        let constraints: ConstraintHandler = ff.get_handler().unwrap();
+        assert_eq!(constraints.version(), "0.3");
+
+        let bonds: BondHandler = ff.get_handler().unwrap();
+        assert_eq!(bonds.potential(), "harmonic");
+        assert_eq!(bonds.fractional_bondorder_method(), "AM1-Wiberg");
+        assert_eq!(bonds.fractional_bondorder_interpolation(), "linear");
+        assert_eq!(bonds.handler().parameters().len(), 90);
+
+        let elec: ElectrostaticsHandler = ff.get_handler().unwrap();
+        assert_eq!(elec.periodic_potential(), "Ewald3D-ConductingBoundary");
+        assert_eq!(elec.scale14(), 0.8333333333);
+
+        let mut vdw: VdWHandler = ff.get_handler().unwrap();
+        assert_eq!(vdw.combining_rules(), "Lorentz-Berthelot");
+        vdw.set_scale14(0.4);
+        vdw.set_switch_width(elec.switch_width());
+        let vdw: VdWHandler = ff.get_handler().unwrap();
+        assert_eq!(vdw.scale14(), 0.4);
+
+        let h = ff.get_parameter_handler("Angles").unwrap();
+        assert!(BondHandler::try_from(h).is_err());
+    }
+
+    #[test]
+    fn cosmetic_attributes() {
+        let ff = ForceField::load("openff-2.1.0.offxml").unwrap();
+        let mut h = ff.get_parameter_handler("Bonds").unwrap();
+        let mut p = h.get_parameter("b1").unwrap();
+        assert!(p.cosmetic_attributes().is_empty());
+
+        p.add_cosmetic_attribute("parent_id", "b0");
+        p.add_cosmetic_attribute("fit_iteration", "3");
+        h.add_cosmetic_attribute("fit_iteration", "3");
+        assert!(p.attribute_is_cosmetic("parent_id"));
+        assert_eq!(
+            p.cosmetic_attributes(),
+            HashMap::from([
+                ("parent_id".to_owned(), "b0".to_owned()),
+                ("fit_iteration".to_owned(), "3".to_owned()),
+            ])
+        );
+
+        // cosmetic attributes should survive a round trip through a file
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("cosmetic.offxml");
+        let out = out.to_str().unwrap();
+        ff.to_file(out);
+        let ff = ForceField::load(out).unwrap();
+        let mut h = ff.get_parameter_handler("Bonds").unwrap();
+        let mut p = h.get_parameter("b1").unwrap();
+        assert_eq!(p.cosmetic_attributes()["parent_id"], "b0");
+        assert_eq!(h.cosmetic_attributes()["fit_iteration"], "3");
+
+        p.delete_cosmetic_attribute("parent_id").unwrap();
+        assert!(p.delete_cosmetic_attribute("parent_id").is_err());
+        h.delete_cosmetic_attribute("fit_iteration").unwrap();
+
+        // and be dropped on request
+        ff.to_file_discard_cosmetic(out);
+        let ff = ForceField::load(out).unwrap();
+        let h = ff.get_parameter_handler("Bonds").unwrap();
+        let p = h.get_parameter("b1").unwrap();
+        assert!(p.cosmetic_attributes().is_empty());
+    }
 }
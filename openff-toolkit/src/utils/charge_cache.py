@@ -0,0 +1,23 @@
+from openff.toolkit.utils.exceptions import (
+    ChargeCalculationError,
+    ConformerGenerationError,
+)
+from openff.toolkit.utils.toolkits import GLOBAL_TOOLKIT_REGISTRY
+
+FAILURES = (ChargeCalculationError, ConformerGenerationError)
+
+
+def assign_partial_charges(molecule, method):
+    # Molecule.assign_partial_charges wraps every toolkit error in a
+    # ValueError, so call the registry directly to tell failures for this
+    # molecule apart from unsupported methods and missing toolkits
+    try:
+        GLOBAL_TOOLKIT_REGISTRY.call(
+            "assign_partial_charges",
+            molecule=molecule,
+            partial_charge_method=method,
+            raise_exception_types=list(FAILURES),
+        )
+    except FAILURES:
+        return False
+    return True
@@ -0,0 +1,426 @@
+//! A persistent cache of partial charges, keyed by mapped SMILES, charge
+//! method, and toolkit version. Assigning AM1BCC charges is by far the slowest
+//! part of building a system, so [ForceField](crate::ForceField) and the
+//! QCSubmit charge filter look up charges in [ChargeCache::global] before
+//! asking the toolkit to compute them.
+//!
+//! The cache is stored as a tab-separated file with one line per entry. The
+//! fields are the toolkit version, the charge method, the mapped SMILES, and
+//! the comma-separated charges in units of elementary charge, or [FAILED] if
+//! the toolkit could not assign them.
+
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use openff_units::{
+    array::{ndarray::Array1, ArrayQuantity},
+    native::Unit,
+};
+use pyo3::{PyResult, Python};
+use utils::{compile_attr, import_attr};
+
+use crate::Molecule;
+
+/// Environment variable overriding the location of the global cache file.
+pub const CACHE_PATH_VAR: &str = "COPRELOS_CHARGE_CACHE";
+
+/// The charges field of a cache line for [CachedCharges::Failed].
+pub const FAILED: &str = "failed";
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub mapped_smiles: String,
+    pub method: String,
+    pub toolkit_version: String,
+}
+
+impl CacheKey {
+    /// Construct the key for `molecule` and `method` under the currently
+    /// installed version of the toolkit.
+    pub fn new(molecule: &Molecule, method: &str) -> Self {
+        Self {
+            mapped_smiles: molecule.to_mapped_smiles(),
+            method: method.to_owned(),
+            toolkit_version: toolkit_version(),
+        }
+    }
+}
+
+/// The result of assigning partial charges to a molecule, as stored in the
+/// cache.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CachedCharges {
+    /// The charges in units of elementary charge.
+    Assigned(Vec<f64>),
+    /// The toolkit raised a `ChargeCalculationError` or a
+    /// `ConformerGenerationError`.
+    Failed,
+}
+
+/// Counts of cache lookups and entries since the cache was opened.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub entries: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct ChargeCache {
+    /// The backing file, or `None` for a cache that only lives in memory.
+    path: Option<PathBuf>,
+    entries: HashMap<CacheKey, CachedCharges>,
+    hits: usize,
+    misses: usize,
+}
+
+/// Return the version of the installed toolkit.
+fn toolkit_version() -> String {
+    Python::with_gil(|py| {
+        import_attr(py, "openff.toolkit", "__version__")
+            .unwrap()
+            .extract()
+            .unwrap()
+    })
+}
+
+/// Set the partial charges on `molecule` to `charges`, in units of elementary
+/// charge.
+fn set_charges(molecule: &mut Molecule, charges: Vec<f64>) -> PyResult<()> {
+    let charges = ArrayQuantity::new(
+        Array1::from(charges).into_dyn(),
+        Unit::named("elementary_charge").unwrap(),
+    );
+    molecule.set_partial_charges(charges.to_quantity()?);
+    Ok(())
+}
+
+/// Set the partial charges on `molecule` from `charges`, returning whether
+/// there were any to set.
+fn set_cached(
+    molecule: &mut Molecule,
+    charges: CachedCharges,
+) -> PyResult<bool> {
+    match charges {
+        CachedCharges::Assigned(charges) => {
+            set_charges(molecule, charges)?;
+            Ok(true)
+        }
+        CachedCharges::Failed => Ok(false),
+    }
+}
+
+/// Assign partial charges to `molecule` with the first toolkit in the global
+/// registry that supports `method`. Charge calculation and conformer
+/// generation errors are returned as [CachedCharges::Failed], while other
+/// errors, like no toolkit supporting `method`, are returned as errors.
+fn assign(molecule: &mut Molecule, method: &str) -> PyResult<CachedCharges> {
+    let assigned: bool = Python::with_gil(|py| {
+        compile_attr(
+            py,
+            include_str!("charge_cache.py"),
+            "charge_cache.py",
+            "assign_partial_charges",
+        )?
+        .call1((&molecule.0, method))?
+        .extract()
+    })?;
+    if !assigned {
+        return Ok(CachedCharges::Failed);
+    }
+    Ok(CachedCharges::Assigned(
+        molecule
+            .partial_charges()?
+            .expect("charges assigned without error"),
+    ))
+}
+
+fn parse_line(line: &str) -> Option<(CacheKey, CachedCharges)> {
+    let mut fields = line.split('\t');
+    let toolkit_version = fields.next()?.to_owned();
+    let method = fields.next()?.to_owned();
+    let mapped_smiles = fields.next()?.to_owned();
+    let charges = match fields.next()? {
+        FAILED => CachedCharges::Failed,
+        charges => CachedCharges::Assigned(
+            charges
+                .split(',')
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .ok()?,
+        ),
+    };
+    if fields.next().is_some() {
+        return None;
+    }
+    let key = CacheKey {
+        mapped_smiles,
+        method,
+        toolkit_version,
+    };
+    Some((key, charges))
+}
+
+fn format_line(key: &CacheKey, charges: &CachedCharges) -> String {
+    let charges = match charges {
+        CachedCharges::Assigned(charges) => {
+            let charges: Vec<_> = charges.iter().map(f64::to_string).collect();
+            charges.join(",")
+        }
+        CachedCharges::Failed => FAILED.to_owned(),
+    };
+    format!(
+        "{}\t{}\t{}\t{charges}\n",
+        key.toolkit_version, key.method, key.mapped_smiles,
+    )
+}
+
+impl ChargeCache {
+    /// Open the cache stored at `path`, creating it and any missing parent
+    /// directories if it does not exist yet. Malformed lines are skipped.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut entries = HashMap::new();
+        if path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                if let Some((key, charges)) = parse_line(&line?) {
+                    entries.insert(key, charges);
+                }
+            }
+        }
+        Ok(Self {
+            path: Some(path.to_owned()),
+            entries,
+            ..Default::default()
+        })
+    }
+
+    /// Return a cache that is never written to disk.
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Return the default location of the global cache: the value of
+    /// [CACHE_PATH_VAR] if it is set, or `coprelos/partial_charges.tsv` in the
+    /// user's cache directory.
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os(CACHE_PATH_VAR) {
+            return Some(path.into());
+        }
+        let dir =
+            env::var_os("XDG_CACHE_HOME")
+                .map(PathBuf::from)
+                .or_else(|| {
+                    env::var_os("HOME")
+                        .map(|home| Path::new(&home).join(".cache"))
+                })?;
+        Some(dir.join("coprelos").join("partial_charges.tsv"))
+    }
+
+    /// Return the process-wide cache at [ChargeCache::default_path], falling
+    /// back to an in-memory cache if the file cannot be opened.
+    pub fn global() -> &'static Mutex<ChargeCache> {
+        static CACHE: OnceLock<Mutex<ChargeCache>> = OnceLock::new();
+        CACHE.get_or_init(|| {
+            let cache = Self::default_path()
+                .and_then(|path| Self::open(path).ok())
+                .unwrap_or_default();
+            Mutex::new(cache)
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn get(&mut self, key: &CacheKey) -> Option<&CachedCharges> {
+        match self.entries.get(key) {
+            Some(charges) => {
+                self.hits += 1;
+                Some(charges)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Add `charges` to the cache under `key`, appending them to the backing
+    /// file if there is one.
+    pub fn insert(
+        &mut self,
+        key: CacheKey,
+        charges: CachedCharges,
+    ) -> io::Result<()> {
+        if let Some(path) = &self.path {
+            let mut f =
+                OpenOptions::new().create(true).append(true).open(path)?;
+            f.write_all(format_line(&key, &charges).as_bytes())?;
+        }
+        self.entries.insert(key, charges);
+        Ok(())
+    }
+
+    /// Set the partial charges on `molecule` from the cache, or assign them
+    /// with the toolkit and store them in the cache if they are missing.
+    /// Returns `false`, leaving the charges unset, if the toolkit failed with a
+    /// charge calculation or conformer generation error. These failures are
+    /// cached too, but any other error is returned without being cached.
+    pub fn get_or_assign(
+        &mut self,
+        molecule: &mut Molecule,
+        method: &str,
+    ) -> PyResult<bool> {
+        let key = CacheKey::new(molecule, method);
+        if let Some(charges) = self.get(&key) {
+            return set_cached(molecule, charges.clone());
+        }
+        let charges = assign(molecule, method)?;
+        let assigned = charges != CachedCharges::Failed;
+        self.insert(key, charges)?;
+        Ok(assigned)
+    }
+
+    /// Like [ChargeCache::get_or_assign] for a cache shared between threads,
+    /// like [ChargeCache::global]. `cache` is only locked to look up and
+    /// insert the charges, so missing charges are computed without blocking
+    /// other threads, and the lock is never held while waiting for the GIL.
+    /// Two threads missing the same entry may both compute it.
+    pub fn get_or_assign_shared(
+        cache: &Mutex<Self>,
+        molecule: &mut Molecule,
+        method: &str,
+    ) -> PyResult<bool> {
+        let key = CacheKey::new(molecule, method);
+        let cached = cache.lock().unwrap().get(&key).cloned();
+        if let Some(charges) = cached {
+            return set_cached(molecule, charges);
+        }
+        let charges = assign(molecule, method)?;
+        let assigned = charges != CachedCharges::Failed;
+        cache.lock().unwrap().insert(key, charges)?;
+        Ok(assigned)
+    }
+
+    /// Remove every entry for `mapped_smiles`, regardless of method or toolkit
+    /// version, returning the number of entries removed.
+    pub fn invalidate(&mut self, mapped_smiles: &str) -> io::Result<usize> {
+        self.remove_where(|key| key.mapped_smiles == mapped_smiles)
+    }
+
+    /// Remove every entry computed with a toolkit version other than
+    /// `toolkit_version`, returning the number of entries removed.
+    pub fn prune(&mut self, toolkit_version: &str) -> io::Result<usize> {
+        self.remove_where(|key| key.toolkit_version != toolkit_version)
+    }
+
+    /// Remove all entries from the cache and its backing file.
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.remove_where(|_| true)?;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len(),
+        }
+    }
+
+    fn remove_where(
+        &mut self,
+        pred: impl Fn(&CacheKey) -> bool,
+    ) -> io::Result<usize> {
+        let before = self.entries.len();
+        self.entries.retain(|key, _| !pred(key));
+        if let Some(path) = &self.path {
+            let mut f = File::create(path)?;
+            for (key, charges) in &self.entries {
+                f.write_all(format_line(key, charges).as_bytes())?;
+            }
+        }
+        Ok(before - self.entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(smiles: &str, version: &str) -> CacheKey {
+        CacheKey {
+            mapped_smiles: smiles.to_owned(),
+            method: "am1bcc".to_owned(),
+            toolkit_version: version.to_owned(),
+        }
+    }
+
+    #[test]
+    fn persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("persist.tsv");
+        let path = path.as_path();
+        let water = "[H:2][O:1][H:3]";
+        let charges = CachedCharges::Assigned(vec![-0.834, 0.417, 0.417]);
+        let mut cache = ChargeCache::open(path).unwrap();
+        assert_eq!(cache.get(&key(water, "0.14.5")), None);
+        cache.insert(key(water, "0.14.5"), charges.clone()).unwrap();
+        cache.insert(key(water, "0.14.4"), charges.clone()).unwrap();
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 0,
+                misses: 1,
+                entries: 2
+            }
+        );
+
+        let mut cache = ChargeCache::open(path).unwrap();
+        assert_eq!(cache.get(&key(water, "0.14.5")), Some(&charges));
+        assert_eq!(cache.prune("0.14.5").unwrap(), 1);
+
+        let mut cache = ChargeCache::open(path).unwrap();
+        assert_eq!(cache.stats().entries, 1);
+        assert_eq!(cache.invalidate(water).unwrap(), 1);
+        assert_eq!(cache.get(&key(water, "0.14.5")), None);
+        assert_eq!(ChargeCache::open(path).unwrap().stats().entries, 0);
+    }
+
+    #[test]
+    fn skip_malformed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("malformed.tsv");
+        let path = path.as_path();
+        fs::write(
+            path,
+            "0.14.5\tam1bcc\t[Na+:1]\t1\n\
+             0.14.5\tam1bcc\t[Cl-:1]\tx\n\
+             0.14.5\tam1bcc\t[Br-:1]\tfailed\n\
+             0.14.5\tam1bcc\n",
+        )
+        .unwrap();
+        let mut cache = ChargeCache::open(path).unwrap();
+        assert_eq!(cache.stats().entries, 2);
+        assert_eq!(
+            cache.get(&key("[Na+:1]", "0.14.5")),
+            Some(&CachedCharges::Assigned(vec![1.0]))
+        );
+        assert_eq!(
+            cache.get(&key("[Br-:1]", "0.14.5")),
+            Some(&CachedCharges::Failed)
+        );
+        cache.clear().unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "");
+    }
+}
@@ -1,4 +1,6 @@
+pub mod charge_cache;
+
 pub mod constants {
-    const ALLOWED_AROMATICITY_MODELS: [&str; 1] = ["OEAroModel_MDL"];
+    pub const ALLOWED_AROMATICITY_MODELS: [&str; 1] = ["OEAroModel_MDL"];
     pub const DEFAULT_AROMATICITY_MODEL: &str = ALLOWED_AROMATICITY_MODELS[0];
 }
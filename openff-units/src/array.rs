@@ -0,0 +1,185 @@
+//! Conversions between NumPy-backed Quantities and [ndarray] arrays. Rather
+//! than extracting arrays element by element, these read the array's memory
+//! directly through the Python buffer protocol, either as a borrowed
+//! [ArrayBuffer] view or as a single copy into an owned [ArrayQuantity].
+
+use ndarray::{ArrayD, ArrayViewD, IxDyn, ShapeBuilder};
+use pyo3::{
+    buffer::PyBuffer, exceptions::PyValueError, types::PyByteArray, PyAny,
+    PyObject, PyResult, Python,
+};
+
+pub use ndarray;
+
+use utils::import;
+
+use crate::{
+    native::{Unit, UnitError},
+    unit, Quantity,
+};
+
+/// The float64 memory behind a NumPy array, held through the buffer protocol.
+/// The array cannot be resized while an ArrayBuffer exists, but Python code
+/// can still write to it, so views should not be held across calls back into
+/// Python that might modify the array.
+pub struct ArrayBuffer(PyBuffer<f64>);
+
+impl ArrayBuffer {
+    /// Acquire the buffer of `obj`, converting it to a float64 array first if
+    /// needed. Objects that are already float64 arrays are not copied.
+    pub fn new(obj: &PyAny) -> PyResult<Self> {
+        let numpy = import(obj.py(), "numpy")?;
+        let arr = numpy.call_method1("asarray", (obj, "float64"))?;
+        Ok(Self(PyBuffer::get(arr)?))
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        self.0.shape()
+    }
+
+    /// Return a view of the buffer without copying it, or `None` if the
+    /// buffer has negative strides, which [ndarray] views cannot represent.
+    pub fn view(&self) -> Option<ArrayViewD<'_, f64>> {
+        let item = self.0.item_size() as isize;
+        let strides = self
+            .0
+            .strides()
+            .iter()
+            .map(|&s| (s >= 0 && s % item == 0).then_some((s / item) as usize))
+            .collect::<Option<Vec<_>>>()?;
+        let shape = IxDyn(self.shape()).strides(IxDyn(&strides));
+        // SAFETY: the buffer holds a reference to the exporting object, which
+        // keeps the memory alive and prevents NumPy from reallocating it for
+        // the lifetime of `self`, and the shape and strides come from the
+        // exporter itself
+        unsafe {
+            Some(ArrayViewD::from_shape_ptr(
+                shape,
+                self.0.buf_ptr() as *const f64,
+            ))
+        }
+    }
+
+    /// Copy the buffer into an owned array in standard (C) order.
+    pub fn to_array(&self) -> PyResult<ArrayD<f64>> {
+        let data = Python::with_gil(|py| self.0.to_vec(py))?;
+        ArrayD::from_shape_vec(self.shape(), data)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+}
+
+/// Copy `values` into a new NumPy array with a single memory copy.
+pub fn to_numpy(py: Python<'_>, values: &ArrayD<f64>) -> PyResult<PyObject> {
+    let values = values.as_standard_layout();
+    let data = values.as_slice().unwrap();
+    let bytes = PyByteArray::new_with(py, std::mem::size_of_val(data), |b| {
+        for (chunk, x) in b.chunks_exact_mut(8).zip(data) {
+            chunk.copy_from_slice(&x.to_ne_bytes());
+        }
+        Ok(())
+    })?;
+    let numpy = import(py, "numpy")?;
+    let arr = numpy.call_method1("frombuffer", (bytes, "float64"))?;
+    Ok(arr
+        .call_method1("reshape", (values.shape().to_vec(),))?
+        .into())
+}
+
+/// An array of values with a [Unit], such as the positions of a molecule's
+/// conformer or its partial charges.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArrayQuantity {
+    pub values: ArrayD<f64>,
+    pub unit: Unit,
+}
+
+impl ArrayQuantity {
+    pub fn new(values: ArrayD<f64>, unit: Unit) -> Self {
+        Self { values, unit }
+    }
+
+    /// Return the magnitude of `quantity` as an [ArrayBuffer] along with its
+    /// units, without copying it.
+    pub fn buffer(quantity: &Quantity) -> PyResult<(ArrayBuffer, Unit)> {
+        Python::with_gil(|py| {
+            let m = quantity.0.getattr(py, "magnitude")?;
+            let buf = ArrayBuffer::new(m.as_ref(py))?;
+            Ok((buf, quantity.units().to_string().parse()?))
+        })
+    }
+
+    /// Copy the magnitude of `quantity` into an owned array. Returns an error
+    /// if the units of `quantity` are not supported by [crate::native].
+    pub fn from_quantity(quantity: &Quantity) -> PyResult<Self> {
+        let (buf, unit) = Self::buffer(quantity)?;
+        Ok(Self::new(buf.to_array()?, unit))
+    }
+
+    /// Convert `self` to a NumPy-backed [Quantity].
+    pub fn to_quantity(&self) -> PyResult<Quantity> {
+        Python::with_gil(|py| {
+            let values = to_numpy(py, &self.values)?;
+            Ok(Quantity::new(values, unit().get(&self.unit.to_string())?))
+        })
+    }
+
+    /// Convert `self` to `unit`, returning an error if the dimensions of
+    /// `unit` do not match those of `self`.
+    pub fn to(&self, unit: &Unit) -> Result<Self, UnitError> {
+        let factor = self.unit.conversion_factor(unit)?;
+        Ok(Self::new(&self.values * factor, unit.clone()))
+    }
+
+    /// Return the magnitude of `self` after converting it to `unit`.
+    pub fn m_as(&self, unit: &Unit) -> Result<ArrayD<f64>, UnitError> {
+        Ok(self.to(unit)?.values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let positions = array![[0.0, 0.0, 0.0], [0.9572, 0.0, 0.0]].into_dyn();
+        let q = ArrayQuantity::new(positions, "angstrom".parse().unwrap());
+        let py = q.to_quantity().unwrap();
+        assert_eq!(py.units(), unit().angstrom());
+        assert_eq!(ArrayQuantity::from_quantity(&py).unwrap(), q);
+
+        let (buf, unit) = ArrayQuantity::buffer(&py).unwrap();
+        assert_eq!(buf.shape(), &[2, 3]);
+        assert_eq!(unit, q.unit);
+        assert_eq!(buf.view().unwrap(), q.values.view());
+
+        let nm = q.m_as(&"nanometer".parse().unwrap()).unwrap();
+        assert!((nm[[1, 0]] - 0.09572).abs() < 1e-12);
+    }
+
+    #[test]
+    fn strided() {
+        Python::with_gil(|py| {
+            let numpy = import(py, "numpy").unwrap();
+            let arr = numpy
+                .call_method1("arange", (6.0,))
+                .unwrap()
+                .call_method1("reshape", ((2, 3),))
+                .unwrap();
+            let t = ArrayBuffer::new(arr.getattr("T").unwrap()).unwrap();
+            let view = t.view().unwrap();
+            assert_eq!(view, array![[0., 3.], [1., 4.], [2., 5.]].into_dyn());
+            assert_eq!(t.to_array().unwrap(), view);
+
+            let flipped = numpy.call_method1("flip", (arr, 1)).unwrap();
+            let flipped = ArrayBuffer::new(flipped).unwrap();
+            assert!(flipped.view().is_none());
+            assert_eq!(
+                flipped.to_array().unwrap(),
+                array![[2., 1., 0.], [5., 4., 3.]].into_dyn()
+            );
+        });
+    }
+}
@@ -1,18 +1,166 @@
-use pyo3::{types::PyModule, FromPyObject, IntoPy, Py, PyAny, Python};
+pub mod array;
+pub mod native;
+
+use std::{
+    fmt::{Debug, Display},
+    ops::{Add, Div, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+use pyo3::{
+    basic::CompareOp, exceptions::PyTypeError, sync::GILOnceCell, FromPyObject,
+    IntoPy, Py, PyAny, PyErr, PyObject, PyResult, Python, ToPyObject,
+};
+use utils::{compile_attr, import_attr};
 
 const PYMODULE: &str = "openff.units.units";
 
-#[derive(FromPyObject)]
+/// Return the function `name` from the Python helpers in `units.py`.
+fn helper<'py>(py: Python<'py>, name: &'static str) -> &'py PyAny {
+    compile_attr(py, include_str!("units.py"), "units.py", name).unwrap()
+}
+
+#[derive(Clone, FromPyObject)]
 pub struct Unit(Py<PyAny>);
 
-#[derive(FromPyObject)]
+impl Display for Unit {
+    /// Format `self` as in offxml files, for example
+    /// `angstrom ** -2 * mole ** -1 * kilocalorie ** 1`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s: String = Python::with_gil(|py| {
+            helper(py, "unit_to_string")
+                .call1((&self.0,))
+                .unwrap()
+                .extract()
+                .unwrap()
+        });
+        write!(f, "{s}")
+    }
+}
+
+impl Debug for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl PartialEq for Unit {
+    fn eq(&self, other: &Self) -> bool {
+        Python::with_gil(|py| {
+            self.0
+                .as_ref(py)
+                .rich_compare(&other.0, CompareOp::Eq)
+                .and_then(|b| b.is_true())
+                .unwrap()
+        })
+    }
+}
+
+/// Parse a unit from a name or expression in the shared registry. See
+/// [UnitRegistry::get].
+impl FromStr for Unit {
+    type Err = PyErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        unit().get(s)
+    }
+}
+
+impl Unit {
+    /// Raise `self` to the integer power `n`.
+    pub fn powi(&self, n: i32) -> Self {
+        Python::with_gil(|py| {
+            self.0
+                .call_method1(py, "__pow__", (n,))
+                .unwrap()
+                .extract(py)
+                .unwrap()
+        })
+    }
+
+    fn binary_op(&self, op: &str, rhs: &Unit) -> Self {
+        Python::with_gil(|py| {
+            self.0
+                .call_method1(py, op, (&rhs.0,))
+                .unwrap()
+                .extract(py)
+                .unwrap()
+        })
+    }
+}
+
+impl Mul for &Unit {
+    type Output = Unit;
+
+    fn mul(self, rhs: &Unit) -> Unit {
+        self.binary_op("__mul__", rhs)
+    }
+}
+
+impl Mul for Unit {
+    type Output = Unit;
+
+    fn mul(self, rhs: Unit) -> Unit {
+        &self * &rhs
+    }
+}
+
+impl Div for &Unit {
+    type Output = Unit;
+
+    fn div(self, rhs: &Unit) -> Unit {
+        self.binary_op("__truediv__", rhs)
+    }
+}
+
+impl Div for Unit {
+    type Output = Unit;
+
+    fn div(self, rhs: Unit) -> Unit {
+        &self / &rhs
+    }
+}
+
+/// Construct a scalar Quantity, as in `1.5 * unit().angstrom()`.
+impl Mul<Unit> for f64 {
+    type Output = Quantity;
+
+    fn mul(self, rhs: Unit) -> Quantity {
+        Quantity::new(self, rhs)
+    }
+}
+
+impl Mul<&Unit> for f64 {
+    type Output = Quantity;
+
+    fn mul(self, rhs: &Unit) -> Quantity {
+        Quantity::new(self, rhs.clone())
+    }
+}
+
+/// A wrapper around a Pint Quantity. The arithmetic operators panic if the
+/// units of their operands are incompatible, like adding a length to an
+/// energy. Use [Quantity::checked_add] and [Quantity::checked_sub] to handle
+/// these errors instead.
+#[derive(Clone, FromPyObject)]
 pub struct Quantity(pub Py<PyAny>);
 
+impl IntoPy<Py<PyAny>> for Unit {
+    fn into_py(self, _py: Python<'_>) -> Py<PyAny> {
+        self.0
+    }
+}
+
+impl IntoPy<Py<PyAny>> for Quantity {
+    fn into_py(self, _py: Python<'_>) -> Py<PyAny> {
+        self.0
+    }
+}
+
 impl Quantity {
     pub fn new(value: impl IntoPy<Py<PyAny>>, unit: Unit) -> Self {
         Python::with_gil(|py| {
-            let m = PyModule::import(py, PYMODULE).unwrap();
-            m.getattr("Quantity")
+            import_attr(py, PYMODULE, "Quantity")
                 .unwrap()
                 .call1((value, unit.0))
                 .unwrap()
@@ -20,15 +168,184 @@ impl Quantity {
                 .unwrap()
         })
     }
+
+    /// Return the magnitude of `self` in its current units, for example as an
+    /// `f64` for scalar quantities or a `Vec<f64>` for array quantities.
+    pub fn magnitude_as<T: for<'a> FromPyObject<'a>>(&self) -> PyResult<T> {
+        Python::with_gil(|py| {
+            helper(py, "magnitude").call1((&self.0,))?.extract()
+        })
+    }
+
+    pub fn units(&self) -> Unit {
+        Python::with_gil(|py| {
+            self.0.getattr(py, "units").unwrap().extract(py).unwrap()
+        })
+    }
+
+    /// Convert `self` to `unit`, returning an error if the dimensions of
+    /// `unit` do not match those of `self`.
+    pub fn to(&self, unit: &Unit) -> PyResult<Self> {
+        Python::with_gil(|py| {
+            self.0.call_method1(py, "to", (&unit.0,))?.extract(py)
+        })
+    }
+
+    /// Return the magnitude of `self` after converting it to `unit`. See
+    /// [Quantity::magnitude_as] and [Quantity::to].
+    pub fn m_as<T: for<'a> FromPyObject<'a>>(
+        &self,
+        unit: &Unit,
+    ) -> PyResult<T> {
+        self.to(unit)?.magnitude_as()
+    }
+
+    pub fn checked_add(&self, rhs: &Self) -> PyResult<Self> {
+        self.binary_op("__add__", rhs)
+    }
+
+    pub fn checked_sub(&self, rhs: &Self) -> PyResult<Self> {
+        self.binary_op("__sub__", rhs)
+    }
+
+    /// Call the Python method `op` on `self` with `rhs`. Python reports
+    /// unsupported operand types by returning `NotImplemented` rather than
+    /// raising an error, so that is turned into an error here too.
+    fn binary_op(&self, op: &str, rhs: impl ToPyObject) -> PyResult<Self> {
+        Python::with_gil(|py| {
+            let ret = self.0.call_method1(py, op, (rhs.to_object(py),))?;
+            if ret.is(&py.NotImplemented()) {
+                return Err(PyErr::new::<PyTypeError, _>(format!(
+                    "unsupported operand for {op}"
+                )));
+            }
+            ret.extract(py)
+        })
+    }
+}
+
+impl ToPyObject for Quantity {
+    fn to_object(&self, py: Python<'_>) -> PyObject {
+        self.0.clone_ref(py)
+    }
+}
+
+impl Display for Quantity {
+    /// Format `self` as in offxml files, for example
+    /// `419.98 * angstrom ** -2 * mole ** -1 * kilocalorie ** 1`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s: String = Python::with_gil(|py| {
+            helper(py, "quantity_to_string")
+                .call1((&self.0,))
+                .unwrap()
+                .extract()
+                .unwrap()
+        });
+        write!(f, "{s}")
+    }
+}
+
+impl Debug for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+/// Parse a Quantity from a string like those in offxml files, for example
+/// `419.98 * angstrom ** -2 * mole ** -1 * kilocalorie ** 1`.
+impl FromStr for Quantity {
+    type Err = PyErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Python::with_gil(|py| {
+            import_attr(py, PYMODULE, "Quantity")?
+                .call1((s,))?
+                .extract()
+        })
+    }
 }
 
+/// Quantities compare equal if they have the same value after unit
+/// conversion. Quantities with incompatible units are never equal.
+impl PartialEq for Quantity {
+    fn eq(&self, other: &Self) -> bool {
+        Python::with_gil(|py| {
+            self.0
+                .as_ref(py)
+                .rich_compare(&other.0, CompareOp::Eq)
+                .and_then(|b| b.is_true())
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// Generate an arithmetic operator trait implementation calling the Python
+/// method `$py` for both owned and borrowed Quantities, with a right-hand
+/// side of type `$rhs`.
+macro_rules! impl_op {
+    ($($trait:ident, $method:ident, $py:literal, $rhs:ty$(;)?)*) => {
+        $(impl $trait<$rhs> for &Quantity {
+            type Output = Quantity;
+
+            fn $method(self, rhs: $rhs) -> Quantity {
+                self.binary_op($py, rhs).unwrap()
+            }
+        }
+
+        impl $trait<$rhs> for Quantity {
+            type Output = Quantity;
+
+            fn $method(self, rhs: $rhs) -> Quantity {
+                (&self).$method(rhs)
+            }
+        })*
+    }
+}
+
+impl_op! {
+    Add, add, "__add__", Quantity;
+    Add, add, "__add__", &Quantity;
+    Sub, sub, "__sub__", Quantity;
+    Sub, sub, "__sub__", &Quantity;
+    Mul, mul, "__mul__", Quantity;
+    Mul, mul, "__mul__", &Quantity;
+    Mul, mul, "__mul__", f64;
+    Div, div, "__truediv__", Quantity;
+    Div, div, "__truediv__", &Quantity;
+    Div, div, "__truediv__", f64;
+}
+
+impl Neg for &Quantity {
+    type Output = Quantity;
+
+    fn neg(self) -> Quantity {
+        Python::with_gil(|py| {
+            self.0
+                .call_method0(py, "__neg__")
+                .unwrap()
+                .extract(py)
+                .unwrap()
+        })
+    }
+}
+
+impl Neg for Quantity {
+    type Output = Quantity;
+
+    fn neg(self) -> Quantity {
+        -&self
+    }
+}
+
+/// A wrapper around a Pint UnitRegistry. Units from different registries
+/// cannot be combined, so most code should use the shared registry returned
+/// by [unit] rather than constructing a new one.
 #[derive(FromPyObject)]
 pub struct UnitRegistry(pub(crate) Py<PyAny>);
 
 fn get_defaults_path() -> String {
     Python::with_gil(|py| {
-        let m = PyModule::import(py, PYMODULE).unwrap();
-        m.getattr("get_defaults_path")
+        import_attr(py, PYMODULE, "get_defaults_path")
             .unwrap()
             .call0()
             .unwrap()
@@ -37,26 +354,152 @@ fn get_defaults_path() -> String {
     })
 }
 
-pub fn unit() -> UnitRegistry {
+/// Return the default registry used by the OpenFF packages. The registry is
+/// loaded on the first call and shared by all later calls.
+pub fn unit() -> &'static UnitRegistry {
+    static REGISTRY: GILOnceCell<UnitRegistry> = GILOnceCell::new();
     Python::with_gil(|py| {
-        let m = PyModule::import(py, PYMODULE).unwrap();
-        m.getattr("UnitRegistry")
-            .unwrap()
-            .call1((get_defaults_path(),))
-            .unwrap()
-            .extract()
-            .unwrap()
+        REGISTRY.get_or_init(py, || {
+            import_attr(py, "openff.units", "unit")
+                .unwrap()
+                .extract()
+                .unwrap()
+        })
     })
 }
 
+/// Generate [UnitRegistry] methods returning the unit described by each
+/// expression.
+macro_rules! named_units {
+    ($($name:ident => $expr:literal$(,)?)*) => {
+        impl UnitRegistry {
+            $(#[doc = concat!("Return the unit `", $expr, "`.")]
+            pub fn $name(&self) -> Unit {
+                self.get($expr).unwrap()
+            })*
+        }
+    }
+}
+
+named_units! {
+    angstrom => "angstrom",
+    nanometer => "nanometer",
+    bohr => "bohr",
+    degree => "degree",
+    radian => "radian",
+    kilocalorie_per_mole => "kilocalorie / mole",
+    kilojoule_per_mole => "kilojoule / mole",
+    hartree => "hartree",
+    dalton => "dalton",
+    elementary_charge => "elementary_charge",
+}
+
 impl UnitRegistry {
-    pub fn elementary_charge(&self) -> Unit {
+    /// Load a new registry with the OpenFF unit definitions. Units from this
+    /// registry cannot be combined with those from [unit].
+    pub fn new() -> Self {
         Python::with_gil(|py| {
-            self.0
-                .getattr(py, "elementary_charge")
+            import_attr(py, PYMODULE, "UnitRegistry")
                 .unwrap()
-                .extract(py)
+                .call1((get_defaults_path(),))
+                .unwrap()
+                .extract()
                 .unwrap()
         })
     }
+
+    /// Look up a unit by name, like `angstrom`, or by expression, like
+    /// `kilocalorie / mole / angstrom ** 2`.
+    pub fn get(&self, expr: &str) -> PyResult<Unit> {
+        Python::with_gil(|py| {
+            self.0.call_method1(py, "parse_units", (expr,))?.extract(py)
+        })
+    }
+}
+
+impl Default for UnitRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const K: &str =
+        "419.9785013566 * angstrom ** -2 * mole ** -1 * kilocalorie ** 1";
+
+    #[test]
+    fn round_trip() {
+        let q: Quantity = K.parse().unwrap();
+        assert_eq!(q.to_string(), K);
+        assert_eq!(q.magnitude_as::<f64>().unwrap(), 419.9785013566);
+        assert_eq!(
+            q.units().to_string(),
+            "angstrom ** -2 * mole ** -1 * kilocalorie ** 1"
+        );
+    }
+
+    #[test]
+    fn convert() {
+        let nm: Quantity = "1.0 * nanometer ** 1".parse().unwrap();
+        let angstrom: Quantity = "1.0 * angstrom ** 1".parse().unwrap();
+        let got: f64 = nm.m_as(&angstrom.units()).unwrap();
+        assert!((got - 10.0).abs() < 1e-12);
+        assert_eq!(nm.to(&angstrom.units()).unwrap(), nm);
+
+        let degree: Quantity = "1.0 * degree ** 1".parse().unwrap();
+        assert!(nm.to(&degree.units()).is_err());
+
+        let charges =
+            Quantity::new(vec![0.5, -0.5], unit().elementary_charge());
+        assert_eq!(
+            charges.magnitude_as::<Vec<f64>>().unwrap(),
+            vec![0.5, -0.5]
+        );
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a: Quantity = "1.0 * nanometer ** 1".parse().unwrap();
+        let b: Quantity = "5.0 * angstrom ** 1".parse().unwrap();
+        let want: Quantity = "1.5 * nanometer ** 1".parse().unwrap();
+        assert_eq!(&a + &b, want);
+        assert_eq!(&a - &b, &want / 3.0);
+        assert_eq!(-(&a * 2.0), &b * -4.0);
+        let area = &a * &b;
+        assert_eq!((area / a.clone()).to(&b.units()).unwrap(), b);
+
+        let e: Quantity =
+            "1.0 * kilocalorie ** 1 * mole ** -1".parse().unwrap();
+        assert!(a.checked_add(&e).is_err());
+        assert!(a.checked_sub(&e).is_err());
+        assert!(a.checked_add(&b).is_ok());
+    }
+
+    #[test]
+    fn registry() {
+        let u = unit();
+        assert!(std::ptr::eq(u, unit()));
+        assert_eq!(u.get("nanometer").unwrap(), u.nanometer());
+        assert!(u.get("not_a_unit").is_err());
+
+        let k = u.kilocalorie_per_mole() / u.angstrom().powi(2);
+        assert_eq!(
+            k.to_string(),
+            "kilocalorie ** 1 * mole ** -1 * angstrom ** -2"
+        );
+        assert_eq!(k, "kilocalorie / mole / angstrom ** 2".parse().unwrap());
+
+        let r = 1.0 * u.nanometer();
+        assert_eq!(r.m_as::<f64>(&u.angstrom()).unwrap(), 10.0);
+        let e = 1.0 * u.hartree();
+        let got: f64 = e.m_as(&u.get("zeptojoule").unwrap()).unwrap();
+        assert!((got - 4359.7).abs() < 0.1);
+        let theta = 180.0 * u.degree();
+        let got: f64 = theta.m_as(&u.radian()).unwrap();
+        assert!((got - std::f64::consts::PI).abs() < 1e-12);
+        assert_eq!(u.dalton() * u.bohr(), "dalton * bohr".parse().unwrap());
+    }
 }
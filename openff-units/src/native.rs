@@ -0,0 +1,727 @@
+//! A pure-Rust implementation of the subset of openff-units used by SMIRNOFF
+//! force fields: lengths, angles, molar energies and the force constants built
+//! from them, charges, and masses. [Quantity] values parse from and format to
+//! the unit-expression strings found in offxml files, like
+//! `419.98 * angstrom ** -2 * mole ** -1 * kilocalorie ** 1`, and convert to
+//! and from the Python-backed [crate::Quantity] through those strings.
+//!
+//! Unlike Pint, angles have their own dimension here, so a force constant per
+//! square radian cannot be mixed up with one per square angstrom.
+
+use std::{
+    error::Error,
+    fmt::Display,
+    ops::{Add, Div, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+use pyo3::{exceptions::PyValueError, PyErr};
+use utils::python_repr;
+
+/// The exponents of the base dimensions of a unit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Dimension {
+    pub length: i32,
+    pub mass: i32,
+    pub time: i32,
+    pub substance: i32,
+    pub charge: i32,
+    pub angle: i32,
+}
+
+impl Dimension {
+    pub const DIMENSIONLESS: Self = Self::new(0, 0, 0, 0, 0, 0);
+    pub const LENGTH: Self = Self::new(1, 0, 0, 0, 0, 0);
+    pub const MASS: Self = Self::new(0, 1, 0, 0, 0, 0);
+    pub const SUBSTANCE: Self = Self::new(0, 0, 0, 1, 0, 0);
+    pub const CHARGE: Self = Self::new(0, 0, 0, 0, 1, 0);
+    pub const ANGLE: Self = Self::new(0, 0, 0, 0, 0, 1);
+    pub const ENERGY: Self = Self::new(2, 1, -2, 0, 0, 0);
+
+    const fn new(
+        length: i32,
+        mass: i32,
+        time: i32,
+        substance: i32,
+        charge: i32,
+        angle: i32,
+    ) -> Self {
+        Self {
+            length,
+            mass,
+            time,
+            substance,
+            charge,
+            angle,
+        }
+    }
+
+    fn zip(self, other: Self, f: impl Fn(i32, i32) -> i32) -> Self {
+        Self::new(
+            f(self.length, other.length),
+            f(self.mass, other.mass),
+            f(self.time, other.time),
+            f(self.substance, other.substance),
+            f(self.charge, other.charge),
+            f(self.angle, other.angle),
+        )
+    }
+
+    pub fn powi(self, n: i32) -> Self {
+        self.zip(Self::DIMENSIONLESS, |a, _| a * n)
+    }
+}
+
+impl Mul for Dimension {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| a + b)
+    }
+}
+
+impl Div for Dimension {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| a - b)
+    }
+}
+
+/// The units that can appear in a [Unit], along with their sizes in SI units
+/// (radians for angles and moles for amounts of substance) and dimensions.
+/// Sizes are split into a mantissa and a power of ten so that conversions
+/// between decimal multiples like nanometers and angstroms are exact. The
+/// values of the physical constants match those in Pint.
+const UNITS: [(&str, f64, i32, Dimension); 19] = [
+    ("meter", 1.0, 0, Dimension::LENGTH),
+    ("nanometer", 1.0, -9, Dimension::LENGTH),
+    ("picometer", 1.0, -12, Dimension::LENGTH),
+    ("angstrom", 1.0, -10, Dimension::LENGTH),
+    ("bohr", 5.29177210903, -11, Dimension::LENGTH),
+    ("radian", 1.0, 0, Dimension::ANGLE),
+    ("degree", std::f64::consts::PI / 180.0, 0, Dimension::ANGLE),
+    ("joule", 1.0, 0, Dimension::ENERGY),
+    ("kilojoule", 1.0, 3, Dimension::ENERGY),
+    ("calorie", 4.184, 0, Dimension::ENERGY),
+    ("kilocalorie", 4.184, 3, Dimension::ENERGY),
+    ("hartree", 4.3597447222071, -18, Dimension::ENERGY),
+    ("mole", 1.0, 0, Dimension::SUBSTANCE),
+    ("elementary_charge", 1.602176634, -19, Dimension::CHARGE),
+    ("coulomb", 1.0, 0, Dimension::CHARGE),
+    ("dalton", 1.6605390666, -27, Dimension::MASS),
+    ("amu", 1.6605390666, -27, Dimension::MASS),
+    ("gram", 1.0, -3, Dimension::MASS),
+    ("kilogram", 1.0, 0, Dimension::MASS),
+];
+
+fn lookup(name: &str) -> Option<(f64, i32, Dimension)> {
+    UNITS
+        .iter()
+        .find(|(n, ..)| *n == name)
+        .map(|&(_, mantissa, exp, dim)| (mantissa, exp, dim))
+}
+
+/// Return `x * 10 ** n`, dividing for negative `n` so that results like
+/// `5 * 10 ** -1` are exact.
+fn scale10(x: f64, n: i32) -> f64 {
+    if n < 0 {
+        x / 10f64.powi(-n)
+    } else {
+        x * 10f64.powi(n)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum UnitError {
+    /// The expression could not be parsed. Contains the expression.
+    Parse(String),
+    /// The expression contains a unit not in the table of known units.
+    UnknownUnit(String),
+    /// The dimensions of two units do not match.
+    Dimension(Unit, Unit),
+}
+
+impl Display for UnitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnitError::Parse(s) => write!(f, "failed to parse `{s}`"),
+            UnitError::UnknownUnit(s) => write!(f, "unknown unit `{s}`"),
+            UnitError::Dimension(a, b) => {
+                write!(f, "cannot convert from `{a}` to `{b}`")
+            }
+        }
+    }
+}
+
+impl Error for UnitError {}
+
+impl From<UnitError> for PyErr {
+    fn from(e: UnitError) -> Self {
+        PyValueError::new_err(e.to_string())
+    }
+}
+
+/// A product of named units raised to integer powers. The order of the
+/// factors is preserved so that a unit formats the same way it was parsed, but
+/// it is ignored when comparing units.
+#[derive(Clone, Debug, Default)]
+pub struct Unit {
+    factors: Vec<(&'static str, i32)>,
+}
+
+impl PartialEq for Unit {
+    fn eq(&self, other: &Self) -> bool {
+        self.sorted_factors() == other.sorted_factors()
+    }
+}
+
+impl Eq for Unit {}
+
+impl Unit {
+    pub fn dimensionless() -> Self {
+        Self::default()
+    }
+
+    /// Return the unit called `name`, if it is known.
+    pub fn named(name: &str) -> Option<Self> {
+        let (name, ..) = UNITS.iter().find(|(n, ..)| *n == name)?;
+        Some(Self {
+            factors: vec![(name, 1)],
+        })
+    }
+
+    pub fn factors(&self) -> &[(&'static str, i32)] {
+        &self.factors
+    }
+
+    /// Return the factors of `self` sorted by name. Each name appears at most
+    /// once with a nonzero power, so this is the same for equal units.
+    fn sorted_factors(&self) -> Vec<(&'static str, i32)> {
+        let mut factors = self.factors.clone();
+        factors.sort_unstable();
+        factors
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        self.factors
+            .iter()
+            .fold(Dimension::DIMENSIONLESS, |acc, (n, e)| {
+                acc * lookup(n).unwrap().2.powi(*e)
+            })
+    }
+
+    /// Return the size of `self` in SI units as a mantissa and a power of
+    /// ten.
+    fn size(&self) -> (f64, i32) {
+        self.factors.iter().fold((1.0, 0), |(m, x), (n, e)| {
+            let (mantissa, exp, _) = lookup(n).unwrap();
+            (m * mantissa.powi(*e), x + exp * e)
+        })
+    }
+
+    /// Return the factor converting a magnitude in `self` to one in `other`.
+    pub fn conversion_factor(&self, other: &Unit) -> Result<f64, UnitError> {
+        if self.dimension() != other.dimension() {
+            return Err(UnitError::Dimension(self.clone(), other.clone()));
+        }
+        let (m1, x1) = self.size();
+        let (m2, x2) = other.size();
+        Ok(scale10(m1 / m2, x1 - x2))
+    }
+
+    pub fn powi(&self, n: i32) -> Self {
+        let mut ret = Self::default();
+        for &(name, exp) in &self.factors {
+            ret.push(name, exp * n);
+        }
+        ret
+    }
+
+    /// Multiply `self` by `name ** exp`, combining it with an existing factor
+    /// of the same unit if there is one.
+    fn push(&mut self, name: &'static str, exp: i32) {
+        match self.factors.iter().position(|(n, _)| *n == name) {
+            Some(i) => {
+                self.factors[i].1 += exp;
+                if self.factors[i].1 == 0 {
+                    self.factors.remove(i);
+                }
+            }
+            None if exp != 0 => self.factors.push((name, exp)),
+            None => {}
+        }
+    }
+}
+
+impl Mul for &Unit {
+    type Output = Unit;
+
+    fn mul(self, rhs: &Unit) -> Unit {
+        let mut ret = self.clone();
+        for &(name, exp) in &rhs.factors {
+            ret.push(name, exp);
+        }
+        ret
+    }
+}
+
+impl Div for &Unit {
+    type Output = Unit;
+
+    fn div(self, rhs: &Unit) -> Unit {
+        self * &rhs.powi(-1)
+    }
+}
+
+/// Format `self` as in offxml files, for example
+/// `angstrom ** -2 * mole ** -1 * kilocalorie ** 1`.
+impl Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.factors.is_empty() {
+            return write!(f, "dimensionless");
+        }
+        for (i, (name, exp)) in self.factors.iter().enumerate() {
+            if i > 0 {
+                write!(f, " * ")?;
+            }
+            write!(f, "{name} ** {exp}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Unit {
+    type Err = UnitError;
+
+    /// Parse a unit expression like `kilocalorie / mole / angstrom ** 2`.
+    /// Numeric factors are not allowed; use [Quantity::from_str] for those.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let q: Quantity = s.parse()?;
+        if q.value != 1.0 {
+            return Err(UnitError::Parse(s.to_owned()));
+        }
+        Ok(q.unit)
+    }
+}
+
+/// A scalar value with a [Unit]. Like [crate::Quantity], the arithmetic
+/// operators panic if the units of their operands are incompatible. Use
+/// [Quantity::checked_add] and [Quantity::checked_sub] to handle these errors
+/// instead.
+#[derive(Clone, Debug)]
+pub struct Quantity {
+    value: f64,
+    unit: Unit,
+}
+
+impl Quantity {
+    pub fn new(value: f64, unit: Unit) -> Self {
+        Self { value, unit }
+    }
+
+    /// Return the magnitude of `self` in its current units.
+    pub fn magnitude(&self) -> f64 {
+        self.value
+    }
+
+    pub fn units(&self) -> &Unit {
+        &self.unit
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        self.unit.dimension()
+    }
+
+    /// Convert `self` to `unit`, returning an error if the dimensions of
+    /// `unit` do not match those of `self`.
+    pub fn to(&self, unit: &Unit) -> Result<Self, UnitError> {
+        if self.dimension() != unit.dimension() {
+            return Err(UnitError::Dimension(self.unit.clone(), unit.clone()));
+        }
+        let (m1, x1) = self.unit.size();
+        let (m2, x2) = unit.size();
+        let value = scale10(self.value * (m1 / m2), x1 - x2);
+        Ok(Self::new(value, unit.clone()))
+    }
+
+    /// Return the magnitude of `self` after converting it to `unit`.
+    pub fn m_as(&self, unit: &Unit) -> Result<f64, UnitError> {
+        Ok(self.to(unit)?.value)
+    }
+
+    /// Add `rhs` to `self`, returning the result in the units of `self`.
+    pub fn checked_add(&self, rhs: &Self) -> Result<Self, UnitError> {
+        Ok(Self::new(
+            self.value + rhs.m_as(&self.unit)?,
+            self.unit.clone(),
+        ))
+    }
+
+    /// Subtract `rhs` from `self`, returning the result in the units of
+    /// `self`.
+    pub fn checked_sub(&self, rhs: &Self) -> Result<Self, UnitError> {
+        self.checked_add(&-rhs)
+    }
+}
+
+/// Format `self` as in offxml files, for example
+/// `419.98 * angstrom ** -2 * mole ** -1 * kilocalorie ** 1`. The magnitude is
+/// formatted like Python's `repr` so that the output matches the toolkit's.
+impl Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} * {}", python_repr(self.value), self.unit)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token<'a> {
+    Number(f64),
+    Name(&'a str),
+    Star,
+    Slash,
+    Pow,
+    LParen,
+    RParen,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token<'_>>, UnitError> {
+    let err = || UnitError::Parse(s.to_owned());
+    let bytes = s.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        match c {
+            b' ' | b'\t' | b'\n' => i += 1,
+            b'*' if bytes.get(i + 1) == Some(&b'*') => {
+                tokens.push(Token::Pow);
+                i += 2;
+            }
+            b'*' => {
+                tokens.push(Token::Star);
+                i += 1;
+            }
+            b'/' => {
+                tokens.push(Token::Slash);
+                i += 1;
+            }
+            b'(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            b')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            b'0'..=b'9' | b'.' | b'-' | b'+' => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'0'..=b'9' | b'.' => i += 1,
+                        b'e' | b'E' => {
+                            i += 1;
+                            if matches!(bytes.get(i), Some(b'-' | b'+')) {
+                                i += 1;
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+                let x = s[start..i].parse().map_err(|_| err())?;
+                tokens.push(Token::Number(x));
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                let start = i;
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_')
+                {
+                    i += 1;
+                }
+                tokens.push(Token::Name(&s[start..i]));
+            }
+            _ => return Err(err()),
+        }
+    }
+    Ok(tokens)
+}
+
+/// A recursive-descent parser for the grammar
+///
+/// ```text
+/// expr   := power (('*' | '/') power)*
+/// power  := factor ('**' number)?
+/// factor := number | name | '(' expr ')'
+/// ```
+struct Parser<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    input: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn err(&self) -> UnitError {
+        UnitError::Parse(self.input.to_owned())
+    }
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos)
+    }
+
+    fn expr(&mut self) -> Result<Quantity, UnitError> {
+        let mut ret = self.power()?;
+        loop {
+            match self.peek() {
+                Some(Token::Star) => {
+                    self.pos += 1;
+                    ret = &ret * &self.power()?;
+                }
+                Some(Token::Slash) => {
+                    self.pos += 1;
+                    ret = &ret / &self.power()?;
+                }
+                _ => return Ok(ret),
+            }
+        }
+    }
+
+    fn power(&mut self) -> Result<Quantity, UnitError> {
+        let base = self.factor()?;
+        if self.peek() != Some(&Token::Pow) {
+            return Ok(base);
+        }
+        self.pos += 1;
+        match self.next() {
+            Some(Token::Number(n)) if n.fract() == 0.0 => {
+                let n = n as i32;
+                Ok(Quantity::new(base.value.powi(n), base.unit.powi(n)))
+            }
+            _ => Err(self.err()),
+        }
+    }
+
+    fn factor(&mut self) -> Result<Quantity, UnitError> {
+        match self.next() {
+            Some(Token::Number(x)) => Ok(Quantity::new(x, Unit::default())),
+            Some(Token::Name("dimensionless")) => {
+                Ok(Quantity::new(1.0, Unit::default()))
+            }
+            Some(Token::Name(name)) => {
+                let unit = Unit::named(name)
+                    .ok_or_else(|| UnitError::UnknownUnit(name.to_owned()))?;
+                Ok(Quantity::new(1.0, unit))
+            }
+            Some(Token::LParen) => {
+                let ret = self.expr()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(ret),
+                    _ => Err(self.err()),
+                }
+            }
+            _ => Err(self.err()),
+        }
+    }
+}
+
+/// Parse a Quantity from a string like those in offxml files, for example
+/// `419.98 * angstrom ** -2 * mole ** -1 * kilocalorie ** 1`.
+impl FromStr for Quantity {
+    type Err = UnitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+            input: s,
+        };
+        let ret = parser.expr()?;
+        if parser.pos != parser.tokens.len() {
+            return Err(parser.err());
+        }
+        Ok(ret)
+    }
+}
+
+/// Quantities compare equal if they have the same value after unit
+/// conversion. Quantities with incompatible units are never equal.
+impl PartialEq for Quantity {
+    fn eq(&self, other: &Self) -> bool {
+        other.m_as(&self.unit).is_ok_and(|v| v == self.value)
+    }
+}
+
+impl Add for &Quantity {
+    type Output = Quantity;
+
+    fn add(self, rhs: &Quantity) -> Quantity {
+        self.checked_add(rhs).unwrap()
+    }
+}
+
+impl Sub for &Quantity {
+    type Output = Quantity;
+
+    fn sub(self, rhs: &Quantity) -> Quantity {
+        self.checked_sub(rhs).unwrap()
+    }
+}
+
+impl Mul for &Quantity {
+    type Output = Quantity;
+
+    fn mul(self, rhs: &Quantity) -> Quantity {
+        Quantity::new(self.value * rhs.value, &self.unit * &rhs.unit)
+    }
+}
+
+impl Div for &Quantity {
+    type Output = Quantity;
+
+    fn div(self, rhs: &Quantity) -> Quantity {
+        Quantity::new(self.value / rhs.value, &self.unit / &rhs.unit)
+    }
+}
+
+impl Mul<f64> for &Quantity {
+    type Output = Quantity;
+
+    fn mul(self, rhs: f64) -> Quantity {
+        Quantity::new(self.value * rhs, self.unit.clone())
+    }
+}
+
+impl Div<f64> for &Quantity {
+    type Output = Quantity;
+
+    fn div(self, rhs: f64) -> Quantity {
+        Quantity::new(self.value / rhs, self.unit.clone())
+    }
+}
+
+impl Neg for &Quantity {
+    type Output = Quantity;
+
+    fn neg(self) -> Quantity {
+        Quantity::new(-self.value, self.unit.clone())
+    }
+}
+
+impl TryFrom<&crate::Quantity> for Quantity {
+    type Error = UnitError;
+
+    /// Convert a scalar Python-backed Quantity through its offxml string.
+    fn try_from(q: &crate::Quantity) -> Result<Self, Self::Error> {
+        q.to_string().parse()
+    }
+}
+
+impl From<&Quantity> for crate::Quantity {
+    fn from(q: &Quantity) -> Self {
+        q.to_string()
+            .parse()
+            .expect("native units are a subset of openff-units")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const K: &str =
+        "419.9785013566 * angstrom ** -2 * mole ** -1 * kilocalorie ** 1";
+
+    #[test]
+    fn round_trip() {
+        let q: Quantity = K.parse().unwrap();
+        assert_eq!(q.to_string(), K);
+        assert_eq!(q.magnitude(), 419.9785013566);
+        assert_eq!(
+            q.dimension(),
+            Dimension::ENERGY / Dimension::new(2, 0, 0, 1, 0, 0)
+        );
+
+        let tests = [
+            ("1.0 * angstrom", "1.0 * angstrom ** 1"),
+            ("-0.5 * elementary_charge", "-0.5 * elementary_charge ** 1"),
+            ("1e-05 * dimensionless", "1e-05 * dimensionless"),
+            ("2.5e+16 * dalton", "2.5e+16 * dalton ** 1"),
+            ("0.0001 * radian ** -2", "0.0001 * radian ** -2"),
+            (
+                "3 * kilocalorie / mole / (angstrom ** 2)",
+                "3.0 * kilocalorie ** 1 * mole ** -1 * angstrom ** -2",
+            ),
+            ("180.0 * degree * degree / degree", "180.0 * degree ** 1"),
+        ];
+        for (input, want) in tests {
+            let got: Quantity = input.parse().unwrap();
+            assert_eq!(got.to_string(), want, "{input}");
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            "1.0 * furlong".parse::<Quantity>().unwrap_err(),
+            UnitError::UnknownUnit("furlong".to_owned())
+        );
+        assert!("1.0 * * angstrom".parse::<Quantity>().is_err());
+        assert!("(1.0 * angstrom".parse::<Quantity>().is_err());
+        assert!("angstrom ** 0.5".parse::<Quantity>().is_err());
+        assert!("2 * angstrom".parse::<Unit>().is_err());
+    }
+
+    #[test]
+    fn convert() {
+        let nm: Quantity = "1.0 * nanometer".parse().unwrap();
+        let angstrom: Unit = "angstrom".parse().unwrap();
+        assert_eq!(nm.m_as(&angstrom).unwrap(), 10.0);
+        assert_eq!(nm.to(&angstrom).unwrap(), nm);
+        assert!(nm.to(&"degree".parse().unwrap()).is_err());
+
+        let a: Unit = "kilocalorie / mole / angstrom ** 2".parse().unwrap();
+        let b: Unit = "kilocalorie / angstrom ** 2 / mole".parse().unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a.to_string(), b.to_string());
+        assert_ne!(a, "kilocalorie / mole".parse().unwrap());
+
+        let theta: Quantity = "180.0 * degree".parse().unwrap();
+        let got = theta.m_as(&"radian".parse().unwrap()).unwrap();
+        assert!((got - std::f64::consts::PI).abs() < 1e-12);
+
+        let k: Quantity = K.parse().unwrap();
+        let kj: Unit = "kilojoule / mole / nanometer ** 2".parse().unwrap();
+        let got = k.m_as(&kj).unwrap();
+        assert!((got - 175719.0049676).abs() < 1e-6);
+    }
+
+    #[test]
+    fn python_round_trip() {
+        let py: crate::Quantity = K.parse().unwrap();
+        let native = Quantity::try_from(&py).unwrap();
+        assert_eq!(native.to_string(), K);
+        assert_eq!(crate::Quantity::from(&native), py);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a: Quantity = "1.0 * nanometer".parse().unwrap();
+        let b: Quantity = "5.0 * angstrom".parse().unwrap();
+        let want: Quantity = "1.5 * nanometer".parse().unwrap();
+        assert_eq!(&a + &b, want);
+        assert_eq!(&a - &b, &want / 3.0);
+        assert_eq!(-&(&a * 2.0), &b * -4.0);
+        let area = &a * &b;
+        assert_eq!((&area / &a).to(b.units()).unwrap(), b);
+
+        let e: Quantity = "1.0 * kilocalorie / mole".parse().unwrap();
+        assert!(a.checked_add(&e).is_err());
+        assert!(a.checked_sub(&e).is_err());
+    }
+}
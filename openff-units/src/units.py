@@ -0,0 +1,17 @@
+def magnitude(quantity):
+    m = quantity.magnitude
+    return m.tolist() if hasattr(m, "tolist") else m
+
+
+def unit_to_string(unit):
+    """Format `unit` like `openff.toolkit.utils.utils.unit_to_string`, as in
+    offxml files"""
+    if unit.dimensionless:
+        return "dimensionless"
+    return " * ".join(f"{name} ** {exp}" for name, exp in unit._units.items())
+
+
+def quantity_to_string(quantity):
+    """Format `quantity` like `openff.toolkit.utils.utils.quantity_to_string`,
+    as in offxml files"""
+    return f"{magnitude(quantity)} * {unit_to_string(quantity.units)}"
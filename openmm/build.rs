@@ -0,0 +1,9 @@
+//! Set the runtime search path for libpython. See `utils/rpath.rs`.
+
+#[path = "../utils/rpath.rs"]
+mod rpath;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    rpath::link_python();
+}
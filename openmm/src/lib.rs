@@ -1,5 +1,35 @@
-use pyo3::{FromPyObject, Py, PyAny};
+use pyo3::{FromPyObject, Py, PyAny, Python};
 
-#[allow(unused)]
 #[derive(FromPyObject)]
 pub struct System(pub(crate) Py<PyAny>);
+
+impl System {
+    /// Return the number of particles in `self`, including virtual sites.
+    pub fn get_num_particles(&self) -> usize {
+        Python::with_gil(|py| {
+            self.0
+                .call_method0(py, "getNumParticles")
+                .unwrap()
+                .extract(py)
+                .unwrap()
+        })
+    }
+
+    /// Report whether the particle at `index` is a virtual site.
+    pub fn is_virtual_site(&self, index: usize) -> bool {
+        Python::with_gil(|py| {
+            self.0
+                .call_method1(py, "isVirtualSite", (index,))
+                .unwrap()
+                .extract(py)
+                .unwrap()
+        })
+    }
+
+    /// Return the indices of all of the virtual site particles in `self`.
+    pub fn virtual_sites(&self) -> Vec<usize> {
+        (0..self.get_num_particles())
+            .filter(|&i| self.is_virtual_site(i))
+            .collect()
+    }
+}
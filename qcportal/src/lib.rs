@@ -1,6 +1,7 @@
 //! Modeled after MolSSI's new QCPortal API
 
-use pyo3::{types::PyModule, FromPyObject, IntoPy, Py, PyAny, Python};
+use pyo3::{FromPyObject, IntoPy, Py, PyAny, Python};
+use utils::import_attr;
 
 #[derive(FromPyObject)]
 pub struct PortalClient(Py<PyAny>);
@@ -8,8 +9,7 @@ pub struct PortalClient(Py<PyAny>);
 impl PortalClient {
     pub fn new(address: &str) -> Self {
         Python::with_gil(|py| {
-            let m = PyModule::import(py, "qcportal").unwrap();
-            m.getattr("PortalClient")
+            import_attr(py, "qcportal", "PortalClient")
                 .unwrap()
                 .call1((address,))
                 .unwrap()
@@ -25,11 +25,169 @@ impl IntoPy<Py<PyAny>> for PortalClient {
     }
 }
 
+pub mod molecules {
+    use openff_units::{
+        array::{ArrayBuffer, ArrayQuantity},
+        native::Unit,
+    };
+    use pyo3::{
+        exceptions::PyValueError, FromPyObject, Py, PyAny, PyResult, Python,
+    };
+    use utils::{get_props, smiles::bracket_atoms};
+
+    /// Return the element symbol and atom map number of each atom in the
+    /// mapped SMILES `cmiles`, in the order they appear in the string.
+    fn atom_maps(cmiles: &str) -> Result<Vec<(String, usize)>, String> {
+        bracket_atoms(cmiles)?
+            .into_iter()
+            .map(|atom| match atom.map {
+                Some(map) if map > 0 => Ok((atom.symbol, map)),
+                _ => Err(format!("unmapped atom {} in {cmiles}", atom.symbol)),
+            })
+            .collect()
+    }
+
+    /// A QCArchive molecule. Unlike the OpenFF toolkit's `Molecule`, this has
+    /// no bonds or formal charges, only atoms and their positions, which are
+    /// stored in bohr.
+    #[derive(Clone, FromPyObject)]
+    pub struct Molecule(Py<PyAny>);
+
+    impl Molecule {
+        /// Return the positions of the atoms in `self` as an array of shape
+        /// `(n_atoms, 3)` in bohr.
+        pub fn geometry(&self) -> ArrayQuantity {
+            let (buf, unit) = self.geometry_buffer().unwrap();
+            ArrayQuantity::new(buf.to_array().unwrap(), unit)
+        }
+
+        /// Return the geometry of `self` as a buffer that can be viewed without
+        /// copying it, along with its units. See [Molecule::geometry].
+        pub fn geometry_buffer(&self) -> PyResult<(ArrayBuffer, Unit)> {
+            Python::with_gil(|py| {
+                let geom = self.0.getattr(py, "geometry")?;
+                let buf = ArrayBuffer::new(geom.as_ref(py))?;
+                Ok((buf, Unit::named("bohr").unwrap()))
+            })
+        }
+
+        /// Return the geometry of `self` in angstrom, checked against the
+        /// mapped SMILES `cmiles` of the entry it came from. Atom map number
+        /// `i` in `cmiles` refers to atom `i - 1` of `self`, which is also the
+        /// order of the atoms in the OpenFF molecule built with
+        /// `Molecule::from_mapped_smiles`, so the rows line up with that
+        /// molecule. An error is returned if the maps do not cover every atom
+        /// exactly once or if the elements they point to do not match, rather
+        /// than returning positions for the wrong atoms.
+        pub fn geometry_for_cmiles(
+            &self,
+            cmiles: &str,
+        ) -> PyResult<ArrayQuantity> {
+            let atoms = atom_maps(cmiles).map_err(PyValueError::new_err)?;
+            check_maps(&atoms, &self.symbols())
+                .map_err(PyValueError::new_err)?;
+            Ok(self.geometry().to(&Unit::named("angstrom").unwrap())?)
+        }
+
+        get_props! {
+            symbols, Vec<String>;
+            molecular_charge, f64;
+            molecular_multiplicity, f64;
+        }
+    }
+
+    /// Check that the atom map numbers in `atoms` cover each of `symbols`
+    /// exactly once and point to atoms of the same element.
+    fn check_maps(
+        atoms: &[(String, usize)],
+        symbols: &[String],
+    ) -> Result<(), String> {
+        if atoms.len() != symbols.len() {
+            return Err(format!(
+                "mapped SMILES has {} atoms, but the molecule has {}",
+                atoms.len(),
+                symbols.len()
+            ));
+        }
+        let mut seen = vec![false; atoms.len()];
+        for (symbol, map) in atoms {
+            let row = map - 1;
+            let Some(seen) = seen.get_mut(row) else {
+                return Err(format!("atom map {map} is out of range"));
+            };
+            if std::mem::replace(seen, true) {
+                return Err(format!("atom map {map} appears more than once"));
+            }
+            if !symbols[row].eq_ignore_ascii_case(symbol) {
+                return Err(format!(
+                    "atom map {map} is {symbol} in the mapped SMILES, but \
+                     atom {row} of the molecule is {}",
+                    symbols[row]
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn cmiles_maps() {
+            let cmiles = "[H:4][C:1]([H:5])([H:6])[c:2]1[n:3][se:7]1[Cl:8]";
+            let atoms = atom_maps(cmiles).unwrap();
+            let got: Vec<_> =
+                atoms.iter().map(|(s, m)| (s.as_str(), *m)).collect();
+            assert_eq!(
+                got,
+                vec![
+                    ("H", 4),
+                    ("C", 1),
+                    ("H", 5),
+                    ("H", 6),
+                    ("C", 2),
+                    ("N", 3),
+                    ("Se", 7),
+                    ("Cl", 8)
+                ]
+            );
+            let symbols: Vec<String> =
+                ["C", "C", "N", "H", "H", "H", "Se", "Cl"]
+                    .map(str::to_owned)
+                    .to_vec();
+            assert!(check_maps(&atoms, &symbols).is_ok());
+
+            let mut swapped = symbols.clone();
+            swapped.swap(0, 2);
+            assert!(check_maps(&atoms, &swapped).is_err());
+            assert!(check_maps(&atoms[1..], &symbols[1..]).is_err());
+            assert!(atom_maps("[C:1][C]").is_err());
+        }
+    }
+}
+
 pub mod record_models {
-    use pyo3::{types::PyModule, FromPyObject, IntoPy, Py, PyAny, Python};
+    use std::collections::BTreeMap;
+
+    use openff_units::{
+        array::{
+            ndarray::{ArrayD, IxDyn},
+            ArrayBuffer, ArrayQuantity,
+        },
+        native::{Quantity, Unit},
+    };
+    use pyo3::{
+        exceptions::PyValueError, types::PyDict, FromPyObject, IntoPy, Py,
+        PyAny, PyResult, Python,
+    };
+    use utils::import_attr;
+
+    use crate::molecules::Molecule;
 
     const PYMODULE: &str = "qcportal.record_models";
 
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
     pub enum RecordStatus {
         Cancelled,
         Complete,
@@ -40,11 +198,10 @@ pub mod record_models {
         Waiting,
     }
 
-    impl IntoPy<Py<PyAny>> for RecordStatus {
-        fn into_py(self, py: Python<'_>) -> Py<PyAny> {
-            let m = PyModule::import(py, PYMODULE).unwrap();
-            let en = m.getattr("RecordStatusEnum").unwrap();
-            let attr = match self {
+    impl RecordStatus {
+        /// Return the value of the corresponding `RecordStatusEnum` member.
+        pub fn as_str(&self) -> &'static str {
+            match self {
                 RecordStatus::Complete => "complete",
                 RecordStatus::Cancelled => "cancelled",
                 RecordStatus::Deleted => "deleted",
@@ -52,24 +209,546 @@ pub mod record_models {
                 RecordStatus::Invalid => "invalid",
                 RecordStatus::Running => "running",
                 RecordStatus::Waiting => "waiting",
-            };
-            en.getattr(attr).unwrap().into()
+            }
         }
     }
 
-    #[derive(Clone, FromPyObject)]
+    impl IntoPy<Py<PyAny>> for RecordStatus {
+        fn into_py(self, py: Python<'_>) -> Py<PyAny> {
+            let en = import_attr(py, PYMODULE, "RecordStatusEnum").unwrap();
+            en.getattr(self.as_str()).unwrap().into()
+        }
+    }
+
+    impl<'source> FromPyObject<'source> for RecordStatus {
+        fn extract(ob: &'source PyAny) -> PyResult<Self> {
+            // RecordStatusEnum is a str enum, so its members extract as their
+            // values
+            let status: String = ob.extract()?;
+            Ok(match status.as_str() {
+                "complete" => RecordStatus::Complete,
+                "cancelled" => RecordStatus::Cancelled,
+                "deleted" => RecordStatus::Deleted,
+                "error" => RecordStatus::Error,
+                "invalid" => RecordStatus::Invalid,
+                "running" => RecordStatus::Running,
+                "waiting" => RecordStatus::Waiting,
+                s => {
+                    return Err(PyValueError::new_err(format!(
+                        "unknown record status `{s}`"
+                    )))
+                }
+            })
+        }
+    }
+
+    /// The quantum chemistry part of a specification, shared by single point
+    /// records and the optimizations within optimization and torsion drive
+    /// records.
+    #[derive(Clone, Debug, FromPyObject)]
+    pub struct QCSpecification {
+        pub program: String,
+        /// One of `energy`, `gradient`, `hessian`, `properties`, or
+        /// `deferred`.
+        pub driver: String,
+        pub method: String,
+        pub basis: Option<String>,
+    }
+
+    /// Return the attribute `attr` of `ob` as an array, or `None` if it is
+    /// missing or `None`.
+    fn array_attr(ob: &PyAny, attr: &str) -> PyResult<Option<ArrayD<f64>>> {
+        match ob.getattr(attr) {
+            Ok(v) if !v.is_none() => Ok(Some(ArrayBuffer::new(v)?.to_array()?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Reshape the flat or nested `values` into rows of `ncols` columns.
+    fn into_rows(values: ArrayD<f64>, ncols: usize) -> ArrayD<f64> {
+        let nrows = values.len() / ncols;
+        values.into_shape(IxDyn(&[nrows, ncols])).unwrap()
+    }
+
+    /// Reshape the flat or nested `values` into a square matrix.
+    fn into_square(values: ArrayD<f64>) -> ArrayD<f64> {
+        let n = (values.len() as f64).sqrt().round() as usize;
+        into_rows(values, n)
+    }
+
+    /// The wavefunction of a single point calculation, retrieved with
+    /// [SinglepointRecord::wavefunction]. Only the SCF quantities are
+    /// included, and each is `None` if the program did not store it.
+    #[derive(Clone, Debug)]
+    pub struct Wavefunction {
+        /// The name of the basis set.
+        pub basis: String,
+        /// Whether the alpha and beta quantities are the same, in which case
+        /// only the alpha ones are stored.
+        pub restricted: bool,
+        pub scf_orbitals_a: Option<ArrayD<f64>>,
+        pub scf_orbitals_b: Option<ArrayD<f64>>,
+        pub scf_density_a: Option<ArrayD<f64>>,
+        pub scf_density_b: Option<ArrayD<f64>>,
+        pub scf_fock_a: Option<ArrayD<f64>>,
+        pub scf_fock_b: Option<ArrayD<f64>>,
+        pub scf_eigenvalues_a: Option<ArrayD<f64>>,
+        pub scf_eigenvalues_b: Option<ArrayD<f64>>,
+        pub scf_occupations_a: Option<ArrayD<f64>>,
+        pub scf_occupations_b: Option<ArrayD<f64>>,
+    }
+
+    impl<'source> FromPyObject<'source> for Wavefunction {
+        fn extract(ob: &'source PyAny) -> PyResult<Self> {
+            Ok(Self {
+                basis: ob.getattr("basis")?.getattr("name")?.extract()?,
+                restricted: ob.getattr("restricted")?.extract()?,
+                scf_orbitals_a: array_attr(ob, "scf_orbitals_a")?,
+                scf_orbitals_b: array_attr(ob, "scf_orbitals_b")?,
+                scf_density_a: array_attr(ob, "scf_density_a")?,
+                scf_density_b: array_attr(ob, "scf_density_b")?,
+                scf_fock_a: array_attr(ob, "scf_fock_a")?,
+                scf_fock_b: array_attr(ob, "scf_fock_b")?,
+                scf_eigenvalues_a: array_attr(ob, "scf_eigenvalues_a")?,
+                scf_eigenvalues_b: array_attr(ob, "scf_eigenvalues_b")?,
+                scf_occupations_a: array_attr(ob, "scf_occupations_a")?,
+                scf_occupations_b: array_attr(ob, "scf_occupations_b")?,
+            })
+        }
+    }
+
+    /// The fields shared by every kind of record, like `BaseRecord` in
+    /// Python.
+    pub trait Record {
+        fn id(&self) -> usize;
+    }
+
+    macro_rules! impl_record {
+        ($($name:ident$(,)?)*) => {
+            $(impl Record for $name {
+                fn id(&self) -> usize {
+                    self.id
+                }
+            })*
+        };
+    }
+
+    impl_record! {
+        SinglepointRecord,
+        OptimizationRecord,
+        TorsiondriveRecord,
+    }
+
+    /// A single point energy, gradient, or Hessian calculation. The results
+    /// are read from the underlying Python record on request, since the
+    /// molecule and wavefunction may have to be fetched from the server.
+    #[derive(Clone)]
+    pub struct SinglepointRecord {
+        pub id: usize, // from BaseRecord
+        pub status: RecordStatus,
+        pub specification: QCSpecification,
+        pub molecule_id: usize,
+        record: Py<PyAny>,
+    }
+
+    impl<'source> FromPyObject<'source> for SinglepointRecord {
+        fn extract(ob: &'source PyAny) -> PyResult<Self> {
+            Ok(Self {
+                id: ob.getattr("id")?.extract()?,
+                status: ob.getattr("status")?.extract()?,
+                specification: ob.getattr("specification")?.extract()?,
+                molecule_id: ob.getattr("molecule_id")?.extract()?,
+                record: ob.into(),
+            })
+        }
+    }
+
+    impl SinglepointRecord {
+        /// Return the entry `name` of the record's `properties`, or `None` if
+        /// the record has no properties or the entry is missing or `None`.
+        fn property<'py>(
+            &self,
+            py: Python<'py>,
+            name: &str,
+        ) -> Option<&'py PyAny> {
+            let props = self.record.getattr(py, "properties").ok()?;
+            let v = props.into_ref(py).get_item(name).ok()?;
+            (!v.is_none()).then_some(v)
+        }
+
+        /// Return the `return_result` of the record if it was computed with
+        /// `driver`.
+        fn result_for<'py>(
+            &self,
+            py: Python<'py>,
+            driver: &str,
+        ) -> Option<&'py PyAny> {
+            if self.specification.driver != driver {
+                return None;
+            }
+            let v = self.record.getattr(py, "return_result").ok()?;
+            let v = v.into_ref(py);
+            (!v.is_none()).then_some(v)
+        }
+
+        /// Return the `return_result` of the record if it was computed with
+        /// `driver`, or the property `name` otherwise, as an array.
+        fn array_result(
+            &self,
+            driver: &str,
+            name: &str,
+        ) -> Option<ArrayD<f64>> {
+            Python::with_gil(|py| {
+                let v = self
+                    .result_for(py, driver)
+                    .or_else(|| self.property(py, name))?;
+                ArrayBuffer::new(v).ok()?.to_array().ok()
+            })
+        }
+
+        /// Return the scalar entries of the record's `properties`, such as
+        /// `scf_total_energy` or `calcinfo_nbasis`.
+        pub fn properties(&self) -> Vec<(String, f64)> {
+            Python::with_gil(|py| {
+                let Ok(props) = self.record.getattr(py, "properties") else {
+                    return Vec::new();
+                };
+                let Ok(props) = props.as_ref(py).downcast::<PyDict>() else {
+                    return Vec::new();
+                };
+                props
+                    .iter()
+                    .filter_map(|(k, v)| {
+                        Some((k.extract().ok()?, v.extract().ok()?))
+                    })
+                    .collect()
+            })
+        }
+
+        /// Return the total energy of the molecule in hartree, if the
+        /// calculation finished.
+        pub fn energy(&self) -> Option<Quantity> {
+            Python::with_gil(|py| {
+                let v = self
+                    .property(py, "return_energy")
+                    .or_else(|| self.result_for(py, "energy"))?;
+                Some(Quantity::new(v.extract().ok()?, Unit::named("hartree")?))
+            })
+        }
+
+        /// Return the gradient of the energy with respect to the atomic
+        /// positions, as an array of shape `(n_atoms, 3)` in hartree / bohr.
+        /// This is available from gradient calculations and from Hessian
+        /// calculations that also stored the gradient.
+        pub fn gradient(&self) -> Option<ArrayQuantity> {
+            let values = self.array_result("gradient", "return_gradient")?;
+            let unit = &Unit::named("hartree")? / &Unit::named("bohr")?;
+            Some(ArrayQuantity::new(into_rows(values, 3), unit))
+        }
+
+        /// Return the Hessian of the energy with respect to the atomic
+        /// positions, as an array of shape `(3 * n_atoms, 3 * n_atoms)` in
+        /// hartree / bohr ** 2.
+        pub fn hessian(&self) -> Option<ArrayQuantity> {
+            let values = self.array_result("hessian", "return_hessian")?;
+            let unit = &Unit::named("hartree")? / &Unit::named("bohr")?.powi(2);
+            Some(ArrayQuantity::new(into_square(values), unit))
+        }
+
+        /// Retrieve the molecule the calculation was run on.
+        pub fn molecule(&self) -> Molecule {
+            Python::with_gil(|py| {
+                self.record
+                    .getattr(py, "molecule")
+                    .unwrap()
+                    .extract(py)
+                    .unwrap()
+            })
+        }
+
+        /// Retrieve the wavefunction, if the specification asked for it to be
+        /// stored.
+        pub fn wavefunction(&self) -> PyResult<Option<Wavefunction>> {
+            Python::with_gil(|py| {
+                let wfn = self.record.getattr(py, "wavefunction")?;
+                if wfn.is_none(py) {
+                    return Ok(None);
+                }
+                Ok(Some(wfn.extract(py)?))
+            })
+        }
+    }
+
+    /// The geomeTRIC keywords of an optimization specification. Options that
+    /// are not set are left to the optimizer's defaults.
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct OptimizationKeywords {
+        /// The internal coordinate system, such as `tric` or `dlc`.
+        pub coordsys: Option<String>,
+        /// Distance in angstrom or radians below which constraints are
+        /// enforced exactly.
+        pub enforce: Option<f64>,
+        /// Whether to reset the Hessian when its eigenvalues go bad.
+        pub reset: Option<bool>,
+        /// Whether to use the Q-Chem convergence criteria.
+        pub qccnv: Option<bool>,
+        /// Small eigenvalue threshold for the Hessian.
+        pub epsilon: Option<f64>,
+        pub maxiter: Option<usize>,
+        /// A named set of convergence criteria, such as `GAU`.
+        pub convergence_set: Option<String>,
+    }
+
+    impl<'source> FromPyObject<'source> for OptimizationKeywords {
+        fn extract(ob: &'source PyAny) -> PyResult<Self> {
+            let kw: &PyDict = ob.downcast()?;
+            fn get<'a, T: FromPyObject<'a>>(
+                kw: &'a PyDict,
+                key: &str,
+            ) -> PyResult<Option<T>> {
+                match kw.get_item(key)? {
+                    Some(v) if !v.is_none() => Ok(Some(v.extract()?)),
+                    _ => Ok(None),
+                }
+            }
+            Ok(Self {
+                coordsys: get(kw, "coordsys")?,
+                enforce: get(kw, "enforce")?,
+                reset: get(kw, "reset")?,
+                qccnv: get(kw, "qccnv")?,
+                epsilon: get(kw, "epsilon")?,
+                maxiter: get(kw, "maxiter")?,
+                convergence_set: get(kw, "convergence_set")?,
+            })
+        }
+    }
+
+    #[derive(Clone, Debug, FromPyObject)]
+    pub struct OptimizationSpecification {
+        /// The geometry optimizer, such as `geometric`.
+        pub program: String,
+        pub keywords: OptimizationKeywords,
+        pub qc_specification: QCSpecification,
+    }
+
+    /// A geometry optimization. The molecules and trajectory are fetched from
+    /// the server on request.
+    #[derive(Clone)]
+    pub struct OptimizationRecord {
+        pub id: usize, // from BaseRecord
+        pub status: RecordStatus,
+        pub specification: OptimizationSpecification,
+        pub initial_molecule_id: usize,
+        pub final_molecule_id: Option<usize>,
+        /// The energy of each step of the optimization in hartree, or `None`
+        /// if it has not run yet.
+        pub energies: Option<Vec<f64>>,
+        record: Py<PyAny>,
+    }
+
+    impl<'source> FromPyObject<'source> for OptimizationRecord {
+        fn extract(ob: &'source PyAny) -> PyResult<Self> {
+            Ok(Self {
+                id: ob.getattr("id")?.extract()?,
+                status: ob.getattr("status")?.extract()?,
+                specification: ob.getattr("specification")?.extract()?,
+                initial_molecule_id: ob
+                    .getattr("initial_molecule_id")?
+                    .extract()?,
+                final_molecule_id: ob
+                    .getattr("final_molecule_id")?
+                    .extract()?,
+                energies: ob.getattr("energies")?.extract()?,
+                record: ob.into(),
+            })
+        }
+    }
+
+    impl OptimizationRecord {
+        /// Report whether the optimization finished and produced a final
+        /// molecule.
+        pub fn is_converged(&self) -> bool {
+            self.status == RecordStatus::Complete
+                && self.final_molecule_id.is_some()
+        }
+
+        /// Return the energy of the last step of the optimization in hartree.
+        pub fn final_energy(&self) -> Option<Quantity> {
+            let energy = *self.energies.as_ref()?.last()?;
+            Some(Quantity::new(energy, Unit::named("hartree")?))
+        }
+
+        /// Retrieve the starting molecule of the optimization.
+        pub fn initial_molecule(&self) -> Molecule {
+            Python::with_gil(|py| {
+                self.record
+                    .getattr(py, "initial_molecule")
+                    .unwrap()
+                    .extract(py)
+                    .unwrap()
+            })
+        }
+
+        /// Retrieve the optimized molecule, if the optimization finished.
+        pub fn final_molecule(&self) -> Option<Molecule> {
+            Python::with_gil(|py| {
+                self.record
+                    .getattr(py, "final_molecule")
+                    .unwrap()
+                    .extract(py)
+                    .unwrap()
+            })
+        }
+
+        /// Return the starting positions in angstrom, lined up with the
+        /// atoms of the entry's mapped SMILES `cmiles`. See
+        /// [Molecule::geometry_for_cmiles].
+        pub fn initial_geometry(
+            &self,
+            cmiles: &str,
+        ) -> PyResult<ArrayQuantity> {
+            self.initial_molecule().geometry_for_cmiles(cmiles)
+        }
+
+        /// Return the optimized positions in angstrom, lined up with the
+        /// atoms of the entry's mapped SMILES `cmiles`, or `None` if the
+        /// optimization has not finished. See [Molecule::geometry_for_cmiles].
+        pub fn final_geometry(
+            &self,
+            cmiles: &str,
+        ) -> Option<PyResult<ArrayQuantity>> {
+            Some(self.final_molecule()?.geometry_for_cmiles(cmiles))
+        }
+
+        /// Retrieve the gradient calculation at each step of the
+        /// optimization.
+        pub fn trajectory(&self) -> Vec<SinglepointRecord> {
+            Python::with_gil(|py| {
+                let traj = self.record.getattr(py, "trajectory").unwrap();
+                if traj.is_none(py) {
+                    return Vec::new();
+                }
+                traj.extract(py).unwrap()
+            })
+        }
+    }
+
+    #[derive(Clone, Debug, FromPyObject)]
     pub struct TorsiondriveKeywords {
         pub dihedrals: Vec<(usize, usize, usize, usize)>,
+        /// The spacing of the grid in degrees, one per dihedral.
+        pub grid_spacing: Vec<i32>,
+        /// The range of angles to scan in degrees for each dihedral, if
+        /// limited.
+        pub dihedral_ranges: Option<Vec<(i32, i32)>>,
+        /// The energy decrease in hartree that triggers a new optimization
+        /// from a grid point that was already visited.
+        pub energy_decrease_thresh: Option<f64>,
+        /// Grid points with energies this far above the minimum in hartree
+        /// are not optimized further.
+        pub energy_upper_limit: Option<f64>,
     }
 
-    #[derive(Clone, FromPyObject)]
+    #[derive(Clone, Debug, FromPyObject)]
     pub struct TorsiondriveSpecification {
         pub keywords: TorsiondriveKeywords,
+        pub optimization_specification: OptimizationSpecification,
     }
 
-    #[derive(Clone, FromPyObject)]
+    impl TorsiondriveSpecification {
+        /// Return the quantum chemistry specification of the constrained
+        /// optimizations.
+        pub fn qc_specification(&self) -> &QCSpecification {
+            &self.optimization_specification.qc_specification
+        }
+    }
+
+    /// A point on a torsion drive grid, with one angle in degrees for each of
+    /// the dihedrals being driven.
+    pub type GridPoint = Vec<i32>;
+
+    /// A scan over one or more dihedrals, made up of constrained
+    /// optimizations at each grid point. The results of the optimizations are
+    /// fetched from the server on request.
+    #[derive(Clone)]
     pub struct TorsiondriveRecord {
         pub id: usize, // from BaseRecord
+        pub status: RecordStatus,
         pub specification: TorsiondriveSpecification,
+        record: Py<PyAny>,
+    }
+
+    impl<'source> FromPyObject<'source> for TorsiondriveRecord {
+        fn extract(ob: &'source PyAny) -> PyResult<Self> {
+            Ok(Self {
+                id: ob.getattr("id")?.extract()?,
+                status: ob.getattr("status")?.extract()?,
+                specification: ob.getattr("specification")?.extract()?,
+                record: ob.into(),
+            })
+        }
+    }
+
+    impl TorsiondriveRecord {
+        /// Return the lowest-energy optimization at each grid point.
+        pub fn minimum_optimizations(
+            &self,
+        ) -> BTreeMap<GridPoint, OptimizationRecord> {
+            Python::with_gil(|py| {
+                self.record
+                    .getattr(py, "minimum_optimizations")
+                    .unwrap()
+                    .extract(py)
+                    .unwrap()
+            })
+        }
+
+        /// Return the final energy of the lowest-energy optimization at each
+        /// grid point, in hartree. Grid points whose optimizations have not
+        /// finished are left out.
+        pub fn final_energies(&self) -> BTreeMap<GridPoint, Quantity> {
+            self.minimum_optimizations()
+                .into_iter()
+                .filter_map(|(point, opt)| Some((point, opt.final_energy()?)))
+                .collect()
+        }
+
+        /// Return the optimized positions from the lowest-energy optimization
+        /// at each grid point in angstrom, lined up with the atoms of the
+        /// entry's mapped SMILES `cmiles`. As for
+        /// [TorsiondriveRecord::final_energies], grid points whose
+        /// optimizations have not finished are left out. See
+        /// [OptimizationRecord::final_geometry].
+        pub fn optimized_geometries(
+            &self,
+            cmiles: &str,
+        ) -> PyResult<BTreeMap<GridPoint, ArrayQuantity>> {
+            self.minimum_optimizations()
+                .into_iter()
+                .filter_map(|(point, opt)| {
+                    Some(opt.final_geometry(cmiles)?.map(|geom| (point, geom)))
+                })
+                .collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use openff_units::array::ndarray::{array, Array1};
+
+        use super::*;
+
+        #[test]
+        fn reshape() {
+            let flat = Array1::from_iter((0..6).map(f64::from)).into_dyn();
+            assert_eq!(
+                into_rows(flat, 3),
+                array![[0., 1., 2.], [3., 4., 5.]].into_dyn()
+            );
+            let flat = Array1::from_iter((0..36).map(f64::from)).into_dyn();
+            let square = into_square(flat);
+            assert_eq!(square.shape(), &[6, 6]);
+            assert_eq!(square[[1, 0]], 6.0);
+        }
     }
 }
@@ -1,6 +1,6 @@
-use std::fs::read_to_string;
-
-use openff_qcsubmit::results::OptimizationResultCollection;
+use openff_qcsubmit::results::{
+    native::ResultCollection, OptimizationResultCollection,
+};
 use qcportal::PortalClient;
 
 #[test]
@@ -10,8 +10,9 @@ fn download_opt() {
     let spec_name = "default";
     let dataset =
         OptimizationResultCollection::from_server(client, datasets, spec_name);
-    let got = dataset.json(2);
-    let want = read_to_string("testfiles/download_opt_want.json").unwrap();
-    // I'm not sure this order is deterministic, but it's passing for now.
+    let got = dataset.canonical_json();
+    let want = ResultCollection::parse_file("testfiles/download_opt_want.json")
+        .unwrap()
+        .canonical_json();
     assert_eq!(got, want);
 }
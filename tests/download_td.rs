@@ -1,6 +1,6 @@
-use std::fs::read_to_string;
-
-use openff_qcsubmit::results::TorsionDriveResultCollection;
+use openff_qcsubmit::results::{
+    native::ResultCollection, TorsionDriveResultCollection,
+};
 use qcportal::PortalClient;
 
 #[test]
@@ -11,8 +11,9 @@ fn download_td() {
     let spec_name = "default";
     let dataset =
         TorsionDriveResultCollection::from_server(client, datasets, spec_name);
-    let got = dataset.json(2);
-    let want = read_to_string("testfiles/download_td_want.json").unwrap();
-    // I'm not sure this order is deterministic, but it's passing for now.
+    let got = dataset.canonical_json();
+    let want = ResultCollection::parse_file("testfiles/download_td_want.json")
+        .unwrap()
+        .canonical_json();
     assert_eq!(got, want);
 }
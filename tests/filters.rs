@@ -33,7 +33,7 @@ fn filter_opt_data(
     let key = entries.keys().next().unwrap().to_owned();
     let new_entries = entries[&key]
         .iter()
-        .filter(|entry| !records_to_remove.contains(&entry.record_id()))
+        .filter(|entry| !records_to_remove.contains(&entry.record_id))
         .cloned()
         .collect();
     entries.insert(key.into(), new_entries);
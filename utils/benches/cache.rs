@@ -0,0 +1,63 @@
+//! Compare looking up Python functions and helper shims through the cache in
+//! [utils::cache] against importing and compiling them on every call, using
+//! `json.dumps` and a toy helper so that only the lookup itself is timed. The
+//! effect on the wrappers is measured by the `lookup` benchmarks in
+//! `openff-toolkit`. Run with `cargo bench -p utils`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use pyo3::{types::PyModule, Python};
+
+const CODE: &str = "def scale(x):\n    return 2 * x\n";
+
+fn import(c: &mut Criterion) {
+    let mut group = c.benchmark_group("import");
+    group.bench_function("uncached", |b| {
+        b.iter(|| {
+            Python::with_gil(|py| {
+                PyModule::import(py, "json")
+                    .unwrap()
+                    .getattr("dumps")
+                    .unwrap()
+                    .is_callable()
+            })
+        })
+    });
+    group.bench_function("cached", |b| {
+        b.iter(|| {
+            Python::with_gil(|py| {
+                utils::import_attr(py, "json", "dumps")
+                    .unwrap()
+                    .is_callable()
+            })
+        })
+    });
+    group.finish();
+}
+
+fn compile(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compile");
+    group.bench_function("uncached", |b| {
+        b.iter(|| {
+            Python::with_gil(|py| {
+                PyModule::from_code(py, CODE, "", "")
+                    .unwrap()
+                    .getattr("scale")
+                    .unwrap()
+                    .is_callable()
+            })
+        })
+    });
+    group.bench_function("cached", |b| {
+        b.iter(|| {
+            Python::with_gil(|py| {
+                utils::compile_attr(py, CODE, "bench.py", "scale")
+                    .unwrap()
+                    .is_callable()
+            })
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, import, compile);
+criterion_main!(benches);
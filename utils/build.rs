@@ -0,0 +1,43 @@
+//! Record the git revision of the workspace in `COPRELOS_GIT_REVISION` for
+//! `utils::provenance`, and set the runtime search path for libpython as in
+//! `rpath.rs`. Builds outside of a git checkout, such as from a published
+//! crate, leave the variable unset.
+
+use std::{path::Path, process::Command};
+
+#[path = "rpath.rs"]
+mod rpath;
+
+fn git(args: &[&str]) -> Option<String> {
+    let out = Command::new("git").args(args).output().ok()?;
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).trim().to_owned())
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    rpath::link_python();
+    let Some(rev) = git(&["rev-parse", "HEAD"]) else {
+        return;
+    };
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|s| !s.is_empty());
+    let suffix = if dirty { "-dirty" } else { "" };
+    println!("cargo:rustc-env=COPRELOS_GIT_REVISION={rev}{suffix}");
+
+    // rebuild when HEAD moves or the current branch gets a new commit. The
+    // index is not watched because git rewrites it on every `git add` and
+    // `git status`, which would rebuild everything that depends on this
+    // crate, so the dirty flag can be stale until the next commit
+    if let Some(dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        let dir = Path::new(&dir);
+        let mut watch = vec![dir.join("HEAD")];
+        if let Some(head) = git(&["symbolic-ref", "-q", "HEAD"]) {
+            watch.push(dir.join(head));
+        }
+        for path in watch.into_iter().filter(|p| p.exists()) {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! Embed the directory of the Python library found by PyO3 as a runtime
+//! search path, so that tests and binaries run without `LD_LIBRARY_PATH`.
+//! PyO3 finds the interpreter from `PYO3_PYTHON` or the `python` on the
+//! `PATH`, which covers conda, venv, and pixi environments alike. See
+//! `utils::environment` for the corresponding runtime checks.
+//!
+//! This file is shared by the build script of every crate in the workspace
+//! that links libpython, each of which includes it with `#[path]`, since
+//! build scripts cannot depend on the crates they are building.
+
+pub fn link_python() {
+    println!("cargo:rerun-if-changed={}", file!());
+    let config = pyo3_build_config::get();
+    let windows = std::env::var("CARGO_CFG_TARGET_OS").unwrap() == "windows";
+    if let (Some(lib_dir), false) = (&config.lib_dir, windows) {
+        println!("cargo:rustc-link-arg=-Wl,-rpath,{lib_dir}");
+    }
+}
@@ -0,0 +1,110 @@
+//! A process-wide cache of Python module and attribute handles. Importing a
+//! module that is already in `sys.modules` is cheap, but it still takes the
+//! import lock and a few dictionary lookups, and compiling the Python helper
+//! files embedded in the wrapper crates is not cheap at all. These functions
+//! do that work once and hand out the cached objects afterwards.
+
+use std::{cell::RefCell, collections::BTreeMap};
+
+use pyo3::{sync::GILProtected, types::PyModule, Py, PyAny, PyResult, Python};
+
+use crate::environment::configure;
+
+type Cache =
+    GILProtected<RefCell<BTreeMap<(&'static str, &'static str), Py<PyAny>>>>;
+
+/// Imported modules and their attributes, keyed by module name and attribute
+/// name. Modules themselves are stored under an empty attribute name.
+static IMPORTED: Cache = GILProtected::new(RefCell::new(BTreeMap::new()));
+
+/// Compiled helper modules and their attributes, keyed by file name and
+/// attribute name, as in [IMPORTED].
+static COMPILED: Cache = GILProtected::new(RefCell::new(BTreeMap::new()));
+
+/// Return the entry for `key` in `cache`, calling `init` to create it if it is
+/// missing. The cache is not borrowed while `init` runs, since that may run
+/// arbitrary Python code.
+fn get_or_try_init<'py>(
+    py: Python<'py>,
+    cache: &Cache,
+    key: (&'static str, &'static str),
+    init: impl FnOnce() -> PyResult<&'py PyAny>,
+) -> PyResult<&'py PyAny> {
+    if let Some(obj) = cache.get(py).borrow().get(&key) {
+        return Ok(obj.clone_ref(py).into_ref(py));
+    }
+    let obj = init()?;
+    cache.get(py).borrow_mut().insert(key, obj.into());
+    Ok(obj)
+}
+
+/// Import the Python module `module`, or return it from the cache if it has
+/// been imported through this function before. The environment is set up
+/// with [configure] before the first import.
+pub fn import<'py>(
+    py: Python<'py>,
+    module: &'static str,
+) -> PyResult<&'py PyModule> {
+    let m = get_or_try_init(py, &IMPORTED, (module, ""), || {
+        configure(py)?;
+        Ok(PyModule::import(py, module)?.as_ref())
+    })?;
+    Ok(m.downcast()?)
+}
+
+/// Return the attribute `attr` of the Python module `module`, such as a class
+/// or a function. See [import].
+pub fn import_attr<'py>(
+    py: Python<'py>,
+    module: &'static str,
+    attr: &'static str,
+) -> PyResult<&'py PyAny> {
+    get_or_try_init(py, &IMPORTED, (module, attr), || {
+        import(py, module)?.getattr(attr)
+    })
+}
+
+/// Return the attribute `attr` of the module compiled from the Python source
+/// `code`, usually included with `include_str!`. The source is compiled on the
+/// first call for each `file_name`, which should be unique across the
+/// embedded helper files.
+pub fn compile_attr<'py>(
+    py: Python<'py>,
+    code: &'static str,
+    file_name: &'static str,
+    attr: &'static str,
+) -> PyResult<&'py PyAny> {
+    get_or_try_init(py, &COMPILED, (file_name, attr), || {
+        let m = get_or_try_init(py, &COMPILED, (file_name, ""), || {
+            let name = file_name.trim_end_matches(".py");
+            Ok(PyModule::from_code(py, code, file_name, name)?.as_ref())
+        })?;
+        m.getattr(attr)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cached() {
+        Python::with_gil(|py| {
+            let a = import_attr(py, "json", "dumps").unwrap();
+            let b = import_attr(py, "json", "dumps").unwrap();
+            assert!(a.is(b));
+            assert!(import(py, "json")
+                .unwrap()
+                .is(import(py, "json").unwrap()));
+            assert!(import(py, "not_a_module").is_err());
+            assert!(import_attr(py, "json", "not_an_attr").is_err());
+
+            const CODE: &str = "def f(x):\n    return 2 * x\n";
+            let f = compile_attr(py, CODE, "test_cached.py", "f").unwrap();
+            let g = compile_attr(py, CODE, "test_cached.py", "f").unwrap();
+            assert!(f.is(g));
+            let got: i32 = f.call1((21,)).unwrap().extract().unwrap();
+            assert_eq!(got, 42);
+        })
+    }
+}
@@ -0,0 +1,446 @@
+//! Discovery and checking of the Python environment at runtime. PyO3 embeds
+//! the Python library it was built against, but the packages wrapped by this
+//! workspace usually live in a separate conda environment or virtualenv. The
+//! first import through [crate::import] calls [configure], which finds that
+//! environment's interpreter and adds its site-packages directories to the
+//! embedded interpreter. Call [check] to see which of the wrapped packages are
+//! importable and at which versions.
+
+use std::{
+    env,
+    error::Error,
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use pyo3::{
+    exceptions::PyImportError, sync::GILOnceCell, types::PyModule, PyErr,
+    PyResult, Python,
+};
+
+/// The Python packages wrapped by this workspace, as (distribution name,
+/// module name, minimum supported version).
+pub const PACKAGES: [(&str, &str, &str); 6] = [
+    ("openff-toolkit", "openff.toolkit", "0.14"),
+    ("openff-qcsubmit", "openff.qcsubmit", "0.50"),
+    ("openff-interchange", "openff.interchange", "0.3"),
+    ("openff-units", "openff.units", "0.2"),
+    ("openmm", "openmm", "8.0"),
+    ("qcportal", "qcportal", "0.50"),
+];
+
+/// Where an [Interpreter] was found, in order of precedence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterpreterSource {
+    /// The `PYO3_PYTHON` environment variable, as used by PyO3 at build time.
+    Pyo3Python,
+    /// The `VIRTUAL_ENV` variable set by an activated virtualenv.
+    VirtualEnv,
+    /// The `CONDA_PREFIX` variable set by an activated conda, mamba, or pixi
+    /// environment.
+    Conda,
+}
+
+impl InterpreterSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InterpreterSource::Pyo3Python => "PYO3_PYTHON",
+            InterpreterSource::VirtualEnv => "VIRTUAL_ENV",
+            InterpreterSource::Conda => "CONDA_PREFIX",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Interpreter {
+    pub executable: PathBuf,
+    pub source: InterpreterSource,
+}
+
+/// Return the path of the Python executable inside of the environment rooted
+/// at `prefix`.
+fn prefix_python(prefix: &Path) -> PathBuf {
+    if cfg!(windows) {
+        prefix.join("python.exe")
+    } else {
+        prefix.join("bin").join("python")
+    }
+}
+
+/// Find the interpreter of the active Python environment from the variables
+/// returned by `var`, preferring `PYO3_PYTHON`, then `VIRTUAL_ENV`, then
+/// `CONDA_PREFIX`.
+fn find_interpreter_with(
+    var: impl Fn(&str) -> Option<String>,
+) -> Option<Interpreter> {
+    if let Some(python) = var("PYO3_PYTHON") {
+        return Some(Interpreter {
+            executable: python.into(),
+            source: InterpreterSource::Pyo3Python,
+        });
+    }
+    let sources = [
+        ("VIRTUAL_ENV", InterpreterSource::VirtualEnv),
+        ("CONDA_PREFIX", InterpreterSource::Conda),
+    ];
+    sources.into_iter().find_map(|(name, source)| {
+        var(name).map(|prefix| Interpreter {
+            executable: prefix_python(Path::new(&prefix)),
+            source,
+        })
+    })
+}
+
+/// Find the interpreter of the active Python environment. See
+/// [InterpreterSource] for the order of precedence.
+pub fn find_interpreter() -> Option<Interpreter> {
+    find_interpreter_with(|name| env::var(name).ok())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EnvironmentError {
+    /// The interpreter could not be run. Contains the interpreter and the
+    /// error message.
+    Interpreter(Interpreter, String),
+    /// The interpreter's Python version differs from the embedded one, so its
+    /// compiled packages cannot be loaded.
+    VersionMismatch {
+        interpreter: Interpreter,
+        found: String,
+        embedded: String,
+    },
+    /// A site-packages directory of the interpreter could not be added to the
+    /// embedded interpreter. Contains the directory and the Python error.
+    SiteDirectory(String, String),
+    /// Some packages are missing or older than their minimum versions.
+    Packages(Vec<PackageStatus>),
+}
+
+impl Display for EnvironmentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnvironmentError::Interpreter(interp, e) => write!(
+                f,
+                "failed to run {} from {}: {e}. Point {} at a working Python \
+                 interpreter",
+                interp.executable.display(),
+                interp.source.as_str(),
+                interp.source.as_str(),
+            ),
+            EnvironmentError::VersionMismatch {
+                interpreter,
+                found,
+                embedded,
+            } => write!(
+                f,
+                "{} from {} is Python {found}, but this program was built \
+                 against Python {embedded}. Rebuild with PYO3_PYTHON={} and \
+                 `cargo clean`, or activate an environment with Python \
+                 {embedded}",
+                interpreter.executable.display(),
+                interpreter.source.as_str(),
+                interpreter.executable.display(),
+            ),
+            EnvironmentError::SiteDirectory(dir, e) => {
+                write!(f, "failed to add site directory {dir}: {e}")
+            }
+            EnvironmentError::Packages(packages) => {
+                writeln!(f, "the Python environment is incomplete:")?;
+                for p in packages {
+                    match &p.version {
+                        Some(v) => writeln!(
+                            f,
+                            "  {} {v} is older than the minimum version {}; \
+                             upgrade it with `mamba install -c conda-forge \
+                             '{}>={}'`",
+                            p.name, p.minimum, p.name, p.minimum
+                        )?,
+                        None => writeln!(
+                            f,
+                            "  {} is not importable ({}); install it with \
+                             `mamba install -c conda-forge {}` or activate \
+                             the environment that has it",
+                            p.name,
+                            p.error.as_deref().unwrap_or("unknown error"),
+                            p.name
+                        )?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for EnvironmentError {}
+
+impl From<EnvironmentError> for PyErr {
+    fn from(e: EnvironmentError) -> Self {
+        PyImportError::new_err(e.to_string())
+    }
+}
+
+/// Run `interpreter` to find its Python version and `sys.path`.
+fn query(
+    interpreter: &Interpreter,
+) -> Result<(String, Vec<String>), EnvironmentError> {
+    let err = |e: String| EnvironmentError::Interpreter(interpreter.clone(), e);
+    let out = Command::new(&interpreter.executable)
+        .args([
+            "-c",
+            "import sys; print('%d.%d' % sys.version_info[:2]); \
+             print('\\n'.join(sys.path))",
+        ])
+        .output()
+        .map_err(|e| err(e.to_string()))?;
+    if !out.status.success() {
+        return Err(err(String::from_utf8_lossy(&out.stderr).into_owned()));
+    }
+    let out = String::from_utf8_lossy(&out.stdout);
+    let mut lines = out.lines();
+    let version = lines.next().unwrap_or_default().to_owned();
+    let path = lines.filter(|l| !l.is_empty()).map(str::to_owned).collect();
+    Ok((version, path))
+}
+
+fn embedded_version(py: Python<'_>) -> String {
+    let v = py.version_info();
+    format!("{}.{}", v.major, v.minor)
+}
+
+/// Add the site-packages directories of the interpreter found by
+/// [find_interpreter] to the embedded interpreter, if there is one. This only
+/// does any work on the first call, and it is called automatically by
+/// [crate::import].
+pub fn configure(py: Python<'_>) -> Result<(), EnvironmentError> {
+    static CONFIGURED: GILOnceCell<Result<(), EnvironmentError>> =
+        GILOnceCell::new();
+    CONFIGURED
+        .get_or_init(py, || {
+            let Some(interpreter) = find_interpreter() else {
+                return Ok(());
+            };
+            let (found, path) = py.allow_threads(|| query(&interpreter))?;
+            let embedded = embedded_version(py);
+            if found != embedded {
+                return Err(EnvironmentError::VersionMismatch {
+                    interpreter,
+                    found,
+                    embedded,
+                });
+            }
+            let site_dirs = path.into_iter().filter(|dir| {
+                dir.ends_with("site-packages") || dir.ends_with("dist-packages")
+            });
+            for dir in site_dirs {
+                PyModule::import(py, "site")
+                    .and_then(|site| site.call_method1("addsitedir", (&dir,)))
+                    .map_err(|e| {
+                        EnvironmentError::SiteDirectory(dir, e.to_string())
+                    })?;
+            }
+            Ok(())
+        })
+        .clone()
+}
+
+/// Whether a package from [PACKAGES] can be imported, and at which version.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageStatus {
+    pub name: &'static str,
+    pub module: &'static str,
+    pub minimum: &'static str,
+    /// The installed version, if the package could be imported.
+    pub version: Option<String>,
+    /// The error raised when importing the package, if any.
+    pub error: Option<String>,
+}
+
+impl PackageStatus {
+    /// Report whether the package is importable and at least its minimum
+    /// version. Development builds without a release tag, which versioneer
+    /// reports as `0+untagged...`, are assumed to be new enough.
+    pub fn is_ok(&self) -> bool {
+        self.version.as_deref().is_some_and(|v| {
+            v.contains("+untagged") || version_at_least(v, self.minimum)
+        })
+    }
+}
+
+/// Report whether the leading numeric components of `version` are at least
+/// those of `minimum`, so that `0.14.5` and `0.14.5rc1` both satisfy `0.14`.
+fn version_at_least(version: &str, minimum: &str) -> bool {
+    let parse = |v: &str| -> Option<Vec<u64>> {
+        v.split('.')
+            .map(|c| {
+                let digits: String =
+                    c.chars().take_while(char::is_ascii_digit).collect();
+                digits.parse().ok()
+            })
+            .take_while(Option::is_some)
+            .collect()
+    };
+    match (parse(version), parse(minimum)) {
+        (Some(v), Some(m)) => v >= m,
+        _ => false,
+    }
+}
+
+/// The result of [check].
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvironmentReport {
+    /// The version of the embedded Python, like `3.11`.
+    pub python_version: String,
+    /// The value of `sys.prefix` in the embedded interpreter.
+    pub prefix: String,
+    pub interpreter: Option<Interpreter>,
+    /// The error from [configure], if any.
+    pub error: Option<EnvironmentError>,
+    pub packages: Vec<PackageStatus>,
+}
+
+impl EnvironmentReport {
+    /// Return an error describing every package that is missing or too old,
+    /// or any error from discovering the environment.
+    pub fn ensure(&self) -> Result<(), EnvironmentError> {
+        if let Some(e) = &self.error {
+            return Err(e.clone());
+        }
+        let bad: Vec<_> = self
+            .packages
+            .iter()
+            .filter(|p| !p.is_ok())
+            .cloned()
+            .collect();
+        if bad.is_empty() {
+            Ok(())
+        } else {
+            Err(EnvironmentError::Packages(bad))
+        }
+    }
+}
+
+impl Display for EnvironmentReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "python {} ({})", self.python_version, self.prefix)?;
+        if let Some(interp) = &self.interpreter {
+            writeln!(
+                f,
+                "environment {} ({})",
+                interp.executable.display(),
+                interp.source.as_str()
+            )?;
+        }
+        if let Some(e) = &self.error {
+            writeln!(f, "error: {e}")?;
+        }
+        for p in &self.packages {
+            let status = match (&p.version, p.is_ok()) {
+                (Some(v), true) => v.clone(),
+                (Some(v), false) => format!("{v} (need >= {})", p.minimum),
+                (None, _) => "missing".to_owned(),
+            };
+            writeln!(f, "{:<20}{status}", p.name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Configure the environment with [configure] and report which of
+/// [PACKAGES] can be imported.
+pub fn check() -> EnvironmentReport {
+    Python::with_gil(|py| {
+        let error = configure(py).err();
+        let metadata = PyModule::import(py, "importlib.metadata").unwrap();
+        let packages = PACKAGES
+            .iter()
+            .map(|&(name, module, minimum)| {
+                let status = |version, error| PackageStatus {
+                    name,
+                    module,
+                    minimum,
+                    version,
+                    error,
+                };
+                if let Err(e) = PyModule::import(py, module) {
+                    return status(None, Some(e.to_string()));
+                }
+                let version: PyResult<String> = metadata
+                    .call_method1("version", (name,))
+                    .and_then(|v| v.extract());
+                match version {
+                    Ok(v) => status(Some(v), None),
+                    Err(e) => status(None, Some(e.to_string())),
+                }
+            })
+            .collect();
+        let sys = PyModule::import(py, "sys").unwrap();
+        EnvironmentReport {
+            python_version: embedded_version(py),
+            prefix: sys.getattr("prefix").unwrap().extract().unwrap(),
+            interpreter: find_interpreter(),
+            error,
+            packages,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn interpreter_precedence() {
+        let vars = HashMap::from([
+            ("PYO3_PYTHON", "/usr/bin/python3"),
+            ("VIRTUAL_ENV", "/home/user/.venv"),
+            ("CONDA_PREFIX", "/opt/conda/envs/coprelos"),
+        ]);
+        let find = |skip: &[&str]| {
+            find_interpreter_with(|k| {
+                (!skip.contains(&k))
+                    .then(|| vars.get(k).map(|v| v.to_string()))
+                    .flatten()
+            })
+        };
+        let got = find(&[]).unwrap();
+        assert_eq!(got.source, InterpreterSource::Pyo3Python);
+        assert_eq!(got.executable, Path::new("/usr/bin/python3"));
+
+        let got = find(&["PYO3_PYTHON"]).unwrap();
+        assert_eq!(got.source, InterpreterSource::VirtualEnv);
+        assert_eq!(got.executable, Path::new("/home/user/.venv/bin/python"));
+
+        let got = find(&["PYO3_PYTHON", "VIRTUAL_ENV"]).unwrap();
+        assert_eq!(got.source, InterpreterSource::Conda);
+
+        assert_eq!(find(&["PYO3_PYTHON", "VIRTUAL_ENV", "CONDA_PREFIX"]), None);
+    }
+
+    #[test]
+    fn versions() {
+        assert!(version_at_least("0.14.5", "0.14"));
+        assert!(version_at_least("0.14.5rc1", "0.14"));
+        assert!(version_at_least("8.1.1", "8.0"));
+        assert!(!version_at_least("0.11.4", "0.14"));
+        assert!(!version_at_least("0.9", "0.14"));
+    }
+
+    #[test]
+    fn report() {
+        let report = check();
+        assert_eq!(report.packages.len(), PACKAGES.len());
+        assert!(report.python_version.starts_with("3."));
+        let missing = report.packages.iter().filter(|p| !p.is_ok()).count();
+        match report.ensure() {
+            Ok(()) => assert_eq!(missing, 0),
+            Err(EnvironmentError::Packages(bad)) => {
+                assert_eq!(bad.len(), missing)
+            }
+            Err(e) => panic!("{e}"),
+        }
+    }
+}
@@ -1,3 +1,12 @@
+pub mod cache;
+pub mod environment;
+pub mod provenance;
+pub mod repr;
+pub mod smiles;
+
+pub use cache::{compile_attr, import, import_attr};
+pub use repr::python_repr;
+
 /// Generate methods on `self` to retrieve Python properties with the same name
 /// and with type `return_ty`.
 #[macro_export]
@@ -0,0 +1,177 @@
+//! A record of the software that produced an output file, so that filtered
+//! collections and force fields can be traced back to the exact versions of
+//! the Python packages and of this workspace that wrote them. Use
+//! [Provenance::gather] to collect the versions from the running environment.
+
+use pyo3::{types::PyDict, IntoPy, PyObject, Python};
+
+use crate::{environment::PACKAGES, import};
+
+/// The cheminformatics toolkits used behind openff-toolkit, as (distribution
+/// name, module name). These are recorded alongside [PACKAGES] when they are
+/// installed.
+pub const TOOLKITS: [(&str, &str); 2] =
+    [("rdkit", "rdkit"), ("openeye-toolkits", "openeye")];
+
+/// The version of this workspace.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The git revision this workspace was built from, with a `-dirty` suffix if
+/// there were uncommitted changes, or `None` if it was not built from a git
+/// checkout. The suffix is only updated when HEAD or the current branch moves,
+/// so it can be stale for builds between commits.
+pub const GIT_REVISION: Option<&str> = option_env!("COPRELOS_GIT_REVISION");
+
+/// The prefix of the cosmetic attribute names returned by
+/// [Provenance::cosmetic_attributes].
+pub const COSMETIC_PREFIX: &str = "provenance_";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Provenance {
+    pub version: String,
+    pub git_revision: Option<String>,
+    /// The installed versions of [PACKAGES] and [TOOLKITS], as (distribution
+    /// name, version) pairs in that order. Packages that are not installed
+    /// are left out.
+    pub packages: Vec<(String, String)>,
+}
+
+/// Return the installed version of the distribution `dist`, falling back on
+/// the `__version__` attribute of `module` for packages installed without
+/// metadata, as conda does for some of them.
+fn installed_version(
+    py: Python<'_>,
+    dist: &str,
+    module: &'static str,
+) -> Option<String> {
+    let metadata = import(py, "importlib.metadata").ok()?;
+    if let Ok(v) = metadata.call_method1("version", (dist,)) {
+        return v.extract().ok();
+    }
+    import(py, module)
+        .and_then(|m| m.getattr("__version__"))
+        .and_then(|v| v.extract())
+        .ok()
+}
+
+impl Provenance {
+    /// Collect the versions of [PACKAGES] and [TOOLKITS] from the Python
+    /// environment, along with the version and git revision of this
+    /// workspace.
+    pub fn gather() -> Self {
+        Python::with_gil(|py| {
+            let packages = PACKAGES
+                .iter()
+                .map(|&(dist, module, _)| (dist, module))
+                .chain(TOOLKITS)
+                .filter_map(|(dist, module)| {
+                    Some((
+                        dist.to_owned(),
+                        installed_version(py, dist, module)?,
+                    ))
+                })
+                .collect();
+            Self {
+                version: VERSION.to_owned(),
+                git_revision: GIT_REVISION.map(str::to_owned),
+                packages,
+            }
+        })
+    }
+
+    /// Return the version of this workspace with the git revision as local
+    /// version metadata, like `0.1.0+3f2a9c1e`.
+    pub fn coprelos(&self) -> String {
+        match &self.git_revision {
+            Some(rev) => format!("{}+{rev}", self.version),
+            None => self.version.clone(),
+        }
+    }
+
+    /// Return (name, version) pairs for this workspace, under the name
+    /// `coprelos`, followed by [Provenance::packages].
+    pub fn entries(&self) -> Vec<(String, String)> {
+        std::iter::once(("coprelos".to_owned(), self.coprelos()))
+            .chain(self.packages.iter().cloned())
+            .collect()
+    }
+
+    /// Return [Provenance::entries] as cosmetic attribute names and values for
+    /// an offxml file. The names are prefixed with [COSMETIC_PREFIX] and have
+    /// dashes replaced by underscores, like `provenance_openff_toolkit`.
+    pub fn cosmetic_attributes(&self) -> Vec<(String, String)> {
+        self.entries()
+            .into_iter()
+            .map(|(name, v)| {
+                (format!("{COSMETIC_PREFIX}{}", name.replace('-', "_")), v)
+            })
+            .collect()
+    }
+}
+
+/// Convert to a Python dict of [Provenance::entries], for embedding in the
+/// `provenance` block of a QCSubmit collection.
+impl IntoPy<PyObject> for &Provenance {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new(py);
+        for (name, version) in self.entries() {
+            dict.set_item(name, version).unwrap();
+        }
+        dict.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries() {
+        let p = Provenance {
+            version: "0.1.0".to_owned(),
+            git_revision: Some("3f2a9c1e".to_owned()),
+            packages: vec![
+                ("openff-toolkit".to_owned(), "0.14.5".to_owned()),
+                ("rdkit".to_owned(), "2023.09.1".to_owned()),
+            ],
+        };
+        assert_eq!(p.coprelos(), "0.1.0+3f2a9c1e");
+        assert_eq!(
+            p.cosmetic_attributes(),
+            vec![
+                (
+                    "provenance_coprelos".to_owned(),
+                    "0.1.0+3f2a9c1e".to_owned()
+                ),
+                ("provenance_openff_toolkit".to_owned(), "0.14.5".to_owned()),
+                ("provenance_rdkit".to_owned(), "2023.09.1".to_owned()),
+            ]
+        );
+        Python::with_gil(|py| {
+            let dict = p.into_py(py);
+            let got: Vec<(String, String)> = dict
+                .downcast::<PyDict>(py)
+                .unwrap()
+                .iter()
+                .map(|(k, v)| (k.extract().unwrap(), v.extract().unwrap()))
+                .collect();
+            assert_eq!(got, p.entries());
+        });
+    }
+
+    #[test]
+    fn gather() {
+        let p = Provenance::gather();
+        assert_eq!(p.version, VERSION);
+        assert_eq!(p.git_revision.as_deref(), GIT_REVISION);
+        let order: Vec<_> = PACKAGES
+            .iter()
+            .map(|p| p.0)
+            .chain(TOOLKITS.map(|t| t.0))
+            .collect();
+        let names: Vec<_> = p.packages.iter().map(|p| p.0.as_str()).collect();
+        let mut sorted = names.clone();
+        sorted.sort_by_key(|n| order.iter().position(|o| o == n));
+        assert_eq!(names, sorted);
+    }
+}
@@ -0,0 +1,117 @@
+//! Parsing of the atoms in SMILES strings, enough to read the elements,
+//! formal charges, and atom maps of the mapped SMILES (`cmiles`) stored with
+//! QCArchive entries without going through a cheminformatics toolkit.
+
+/// An atom written in brackets in a SMILES string, like `[N+:3]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BracketAtom {
+    /// The element symbol, capitalized even for aromatic atoms.
+    pub symbol: String,
+    pub charge: i32,
+    /// The atom map number, if there is one.
+    pub map: Option<usize>,
+}
+
+/// Return the bracket atoms in `smiles`, in the order they appear in the
+/// string. Every atom of a mapped SMILES like the `cmiles` of an entry is
+/// written in brackets, so for those this covers the whole molecule,
+/// including its explicit hydrogens.
+pub fn bracket_atoms(smiles: &str) -> Result<Vec<BracketAtom>, String> {
+    let mut atoms = Vec::new();
+    let mut rest = smiles;
+    while let Some(start) = rest.find('[') {
+        let end = rest[start..]
+            .find(']')
+            .ok_or_else(|| format!("unclosed bracket atom in {smiles}"))?;
+        let atom = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        let atom = atom.trim_start_matches(|c: char| c.is_ascii_digit());
+        let symbol = match atom.get(..2) {
+            // two-letter aromatic symbols
+            Some(s @ ("se" | "as" | "te")) => s,
+            _ => {
+                let len = atom
+                    .char_indices()
+                    .skip(1)
+                    .find(|(_, c)| !c.is_ascii_lowercase())
+                    .map_or(atom.len(), |(i, _)| i);
+                if atom.starts_with(|c: char| c.is_ascii_lowercase()) {
+                    &atom[..1]
+                } else {
+                    &atom[..len]
+                }
+            }
+        };
+        let mut chars = symbol.chars();
+        let symbol = match chars.next() {
+            Some(c) => c.to_ascii_uppercase().to_string() + chars.as_str(),
+            None => return Err(format!("missing element in {smiles}")),
+        };
+        let (atom, map) = match atom.rsplit_once(':') {
+            Some((atom, map)) => (
+                atom,
+                Some(map.parse().map_err(|_| {
+                    format!("invalid atom map {map} in {smiles}")
+                })?),
+            ),
+            None => (atom, None),
+        };
+        let charge = charge(atom).ok_or_else(|| {
+            format!("invalid charge on atom {symbol} in {smiles}")
+        })?;
+        atoms.push(BracketAtom {
+            symbol,
+            charge,
+            map,
+        });
+    }
+    Ok(atoms)
+}
+
+/// Return the formal charge at the end of the bracket atom contents
+/// `atom`, written like `+`, `--`, or `+2`, or `None` if it is malformed.
+fn charge(atom: &str) -> Option<i32> {
+    let Some(i) = atom.find(['+', '-']) else {
+        return Some(0);
+    };
+    let c = atom.as_bytes()[i] as char;
+    let sign = if c == '+' { 1 } else { -1 };
+    let rest = &atom[i + 1..];
+    if rest.is_empty() {
+        Some(sign)
+    } else if rest.chars().all(|c| c.is_ascii_digit()) {
+        Some(sign * rest.parse::<i32>().ok()?)
+    } else if rest.chars().all(|r| r == c) {
+        Some(sign * (rest.len() as i32 + 1))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bracket_charges() {
+        let charges: Vec<_> =
+            bracket_atoms("[N@@H+:1][O-:2][Fe+2][S--][C][O+++:3]")
+                .unwrap()
+                .into_iter()
+                .map(|a| (a.symbol, a.charge, a.map))
+                .collect();
+        assert_eq!(
+            charges,
+            vec![
+                ("N".to_owned(), 1, Some(1)),
+                ("O".to_owned(), -1, Some(2)),
+                ("Fe".to_owned(), 2, None),
+                ("S".to_owned(), -2, None),
+                ("C".to_owned(), 0, None),
+                ("O".to_owned(), 3, Some(3)),
+            ]
+        );
+        assert!(bracket_atoms("[N+-]").is_err());
+    }
+}